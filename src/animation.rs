@@ -0,0 +1,197 @@
+//! Views interpolated between keyframes, for rendering fly-arounds.
+//!
+//! Keyframes are read from text with one keyframe per line:
+//!
+//! ```text
+//! # time  lat  lon  roll  scale  [dx  dy]
+//! 0       0    0    0     200
+//! 4       45   90   0     400    0   -50
+//! ```
+//!
+//! The time is in seconds, the angles in degrees, as for
+//! [`Rotation::from_center`], and the scale and the optional camera offset
+//! in pixels. Blank lines and text after `#` are ignored.
+
+use std::{error, fmt};
+
+use crate::sphere::Rotation;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyframeError {
+    /// The text has no keyframes.
+    Empty,
+    /// The line with this number, counting from 1, isn't a keyframe.
+    Malformed(usize),
+    /// The keyframe on this line doesn't come after the one before it.
+    Unordered(usize),
+}
+impl fmt::Display for KeyframeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no keyframes"),
+            Self::Malformed(line) => write!(
+                f,
+                "line {line}: expected time, lat, lon, roll, scale and \
+                 optionally an offset"
+            ),
+            Self::Unordered(line) => {
+                write!(f, "line {line}: keyframe isn't later than the last")
+            }
+        }
+    }
+}
+impl error::Error for KeyframeError {}
+
+/// The parts of a view that can be animated, at a moment in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// Seconds from the start.
+    pub time: f32,
+    pub rot: Rotation,
+    pub scale: f32,
+    pub cam_offset: [f32; 2],
+}
+
+/// A sequence of keyframes in time order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    keyframes: Vec<Keyframe>,
+}
+impl Animation {
+    /// Reads keyframes in the format described in the [module
+    /// documentation](self).
+    pub fn parse(text: &str) -> Result<Self, KeyframeError> {
+        let mut keyframes: Vec<Keyframe> = Vec::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let line_no = n + 1;
+            let line = line.split('#').next().unwrap_or_default();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let values = line
+                .split_whitespace()
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| KeyframeError::Malformed(line_no))?;
+            let (time, lat, lon, roll, scale, cam_offset) = match values[..] {
+                [time, lat, lon, roll, scale] => {
+                    (time, lat, lon, roll, scale, [0.0, 0.0])
+                }
+                [time, lat, lon, roll, scale, dx, dy] => {
+                    (time, lat, lon, roll, scale, [dx, dy])
+                }
+                _ => return Err(KeyframeError::Malformed(line_no)),
+            };
+            if !values.iter().all(|v| v.is_finite()) || scale <= 0.0 {
+                return Err(KeyframeError::Malformed(line_no));
+            }
+            if keyframes.last().is_some_and(|last| time <= last.time) {
+                return Err(KeyframeError::Unordered(line_no));
+            }
+
+            keyframes.push(Keyframe {
+                time,
+                rot: Rotation::from_center(
+                    lat.to_radians(),
+                    lon.to_radians(),
+                    roll.to_radians(),
+                ),
+                scale,
+                cam_offset,
+            });
+        }
+
+        if keyframes.is_empty() {
+            return Err(KeyframeError::Empty);
+        }
+        Ok(Self { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the first keyframe.
+    pub fn start(&self) -> f32 {
+        self.keyframes[0].time
+    }
+
+    /// Time of the last keyframe.
+    pub fn end(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// The view at `time`, held at the first and last keyframes outside
+    /// their span.
+    ///
+    /// Rotations turn at a constant rate between keyframes, the scale
+    /// changes by a constant factor per second, so that zooming looks
+    /// steady, and the offset moves at a constant speed.
+    pub fn at(&self, time: f32) -> Keyframe {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = match next {
+            0 => return self.keyframes[0],
+            n if n == self.keyframes.len() => return self.keyframes[n - 1],
+            n => (self.keyframes[n - 1], self.keyframes[n]),
+        };
+        let t = (time - a.time) / (b.time - a.time);
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        Keyframe {
+            time,
+            rot: a.rot.slerp(b.rot, t),
+            scale: a.scale * (b.scale / a.scale).powf(t),
+            cam_offset: [
+                lerp(a.cam_offset[0], b.cam_offset[0]),
+                lerp(a.cam_offset[1], b.cam_offset[1]),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes_must_be_later() {
+        let text = "0 0 0 0 100\n# turn\n1 10 0 0 100\n1 20 0 0 100\n";
+
+        assert_eq!(Animation::parse(text), Err(KeyframeError::Unordered(4)));
+    }
+
+    #[test]
+    fn text_without_keyframes() {
+        assert_eq!(Animation::parse(""), Err(KeyframeError::Empty));
+        assert_eq!(
+            Animation::parse("# time lat lon roll scale\n\n   \n"),
+            Err(KeyframeError::Empty)
+        );
+    }
+
+    #[test]
+    fn non_finite_values() {
+        assert_eq!(
+            Animation::parse("0 0 0 0 100\n1 NaN 0 0 100"),
+            Err(KeyframeError::Malformed(2))
+        );
+        assert_eq!(
+            Animation::parse("0 0 0 0 inf"),
+            Err(KeyframeError::Malformed(1))
+        );
+    }
+
+    #[test]
+    fn scale_changes_by_a_constant_factor() {
+        let animation =
+            Animation::parse("0 0 0 0 100 0 0\n2 0 0 0 400 10 -20").unwrap();
+        let middle = animation.at(1.0);
+
+        assert!((middle.scale - 200.0).abs() < 1e-3);
+        assert_eq!(middle.cam_offset, [5.0, -10.0]);
+        assert_eq!(animation.at(-1.0).scale, 100.0);
+        assert_eq!(animation.at(3.0).scale, 400.0);
+    }
+}
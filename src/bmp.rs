@@ -0,0 +1,421 @@
+//! Reading of BMP images.
+//!
+//! Supports the `BITMAPINFOHEADER` family of headers (including V4 and V5)
+//! with 8-bit palettised, 24-bit and 32-bit pixels, uncompressed or stored as
+//! `BI_RLE8` or `BI_BITFIELDS`, in either row order.
+
+use std::{error, fmt};
+
+use crate::texture::{Texture, MAX_PIXELS};
+
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_BITFIELDS: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BmpError {
+    /// The data doesn't start with the `BM` signature.
+    NotBmp,
+    /// The data ends before the header or the pixels it describes.
+    Truncated,
+    /// The info header has an unknown size.
+    UnsupportedHeader(u32),
+    UnsupportedBitDepth(u16),
+    UnsupportedCompression(u32),
+    InvalidDimensions {
+        width: i32,
+        height: i32,
+    },
+    /// A pixel refers past the end of the palette.
+    InvalidPaletteIndex(u8),
+}
+impl fmt::Display for BmpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotBmp => write!(f, "not a BMP image"),
+            Self::Truncated => write!(f, "BMP data is truncated"),
+            Self::UnsupportedHeader(size) => {
+                write!(f, "unsupported BMP header of {size} bytes")
+            }
+            Self::UnsupportedBitDepth(bpp) => {
+                write!(f, "unsupported BMP bit depth {bpp}")
+            }
+            Self::UnsupportedCompression(c) => {
+                write!(f, "unsupported BMP compression {c}")
+            }
+            Self::InvalidDimensions { width, height } => {
+                write!(f, "invalid BMP dimensions {width}x{height}")
+            }
+            Self::InvalidPaletteIndex(idx) => {
+                write!(f, "BMP palette has no entry {idx}")
+            }
+        }
+    }
+}
+impl error::Error for BmpError {}
+
+/// How stored pixel values become colours.
+enum Colors {
+    Palette(Vec<u32>),
+    /// Red, green and blue bit masks.
+    Masks([u32; 3]),
+}
+impl Colors {
+    fn color(&self, value: u32) -> Result<u32, BmpError> {
+        match self {
+            Self::Palette(palette) => palette
+                .get(value as usize)
+                .copied()
+                .ok_or(BmpError::InvalidPaletteIndex(value as u8)),
+            Self::Masks([r, g, b]) => Ok((channel(value, *r) << 16)
+                | (channel(value, *g) << 8)
+                | channel(value, *b)),
+        }
+    }
+}
+
+/// Extracts the bits of `value` under `mask`, scaled to 8 bits.
+fn channel(value: u32, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).trailing_ones();
+    let value = (value & mask) >> shift;
+
+    if bits >= 8 {
+        value >> (bits - 8)
+    } else {
+        value * 255 / ((1 << bits) - 1)
+    }
+}
+
+fn read<const N: usize>(bytes: &[u8], at: usize) -> Result<[u8; N], BmpError> {
+    bytes
+        .get(at..at + N)
+        .map(|b| b.try_into().unwrap())
+        .ok_or(BmpError::Truncated)
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<u16, BmpError> {
+    read(bytes, at).map(u16::from_le_bytes)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32, BmpError> {
+    read(bytes, at).map(u32::from_le_bytes)
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> Result<i32, BmpError> {
+    read(bytes, at).map(i32::from_le_bytes)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Texture, BmpError> {
+    if !bytes.starts_with(b"BM") {
+        return Err(BmpError::NotBmp);
+    }
+    let data_offset = read_u32(bytes, 10)? as usize;
+    let header_size = read_u32(bytes, 14)?;
+    if !matches!(header_size, 40 | 52 | 56 | 108 | 124) {
+        return Err(BmpError::UnsupportedHeader(header_size));
+    }
+
+    let (width, height) = (read_i32(bytes, 18)?, read_i32(bytes, 22)?);
+    let bpp = read_u16(bytes, 28)?;
+    let compression = read_u32(bytes, 30)?;
+    let colors_used = read_u32(bytes, 46)?;
+
+    // A negative height marks rows stored from the top down.
+    let top_down = height < 0;
+    let (w, h) = (width.unsigned_abs(), height.unsigned_abs());
+    if width <= 0
+        || height == 0
+        || (w as usize)
+            .checked_mul(h as usize)
+            .is_none_or(|n| n > MAX_PIXELS)
+    {
+        return Err(BmpError::InvalidDimensions { width, height });
+    }
+
+    let colors = match (compression, bpp) {
+        (BI_RGB | BI_RLE8, 8) => {
+            let count = match colors_used {
+                0 => 256,
+                n => n.min(256) as usize,
+            };
+            let start = 14 + header_size as usize;
+            let palette = (0..count)
+                .map(|i| {
+                    let [b, g, r, _] = read(bytes, start + 4 * i)?;
+                    Ok(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+                })
+                .collect::<Result<_, _>>()?;
+
+            Colors::Palette(palette)
+        }
+        (BI_RGB, 24 | 32) => Colors::Masks([0xff0000, 0xff00, 0xff]),
+        // The masks follow a 40-byte header and open the longer ones, so
+        // they're at the same offset either way.
+        (BI_BITFIELDS, 24 | 32) => Colors::Masks([
+            read_u32(bytes, 54)?,
+            read_u32(bytes, 58)?,
+            read_u32(bytes, 62)?,
+        ]),
+        (BI_RGB | BI_BITFIELDS, _) => {
+            return Err(BmpError::UnsupportedBitDepth(bpp));
+        }
+        _ => return Err(BmpError::UnsupportedCompression(compression)),
+    };
+    if compression == BI_RLE8 && (bpp != 8 || top_down) {
+        return Err(BmpError::UnsupportedCompression(compression));
+    }
+
+    let data = bytes.get(data_offset..).ok_or(BmpError::Truncated)?;
+    let (w, h) = (w as usize, h as usize);
+
+    // Values of the stored pixels, bottom-up unless `top_down`.
+    let values: Vec<u32> = if compression == BI_RLE8 {
+        decode_rle8(data, w, h)?
+    } else {
+        let stride = (bpp as usize * w).div_ceil(32) * 4;
+        if data.len() < stride * h {
+            return Err(BmpError::Truncated);
+        }
+
+        data.chunks_exact(stride)
+            .take(h)
+            .flat_map(|row| {
+                let bytes_pp = bpp as usize / 8;
+
+                row[..w * bytes_pp].chunks_exact(bytes_pp).map(|px| {
+                    px.iter()
+                        .rev()
+                        .fold(0, |value, &byte| (value << 8) | byte as u32)
+                })
+            })
+            .collect()
+    };
+
+    let mut pixels = Vec::with_capacity(w * h);
+    for y in 0..h {
+        let row = if top_down { y } else { h - 1 - y };
+
+        for &value in &values[row * w..(row + 1) * w] {
+            pixels.push(colors.color(value)?);
+        }
+    }
+
+    Ok(Texture::new(w as u32, h as u32, pixels))
+}
+
+/// Expands `BI_RLE8` data into palette indices, bottom-up. Pixels the data
+/// skips over are left at index `0`.
+fn decode_rle8(data: &[u8], w: usize, h: usize) -> Result<Vec<u32>, BmpError> {
+    let mut values = vec![0; w * h];
+    let (mut x, mut y) = (0, 0);
+    let mut put = |x: usize, y: usize, value: u8| {
+        if x < w && y < h {
+            values[y * w + x] = value as u32;
+        }
+    };
+
+    let mut at = 0;
+    loop {
+        let [count, value] = read(data, at)?;
+        at += 2;
+
+        match (count, value) {
+            (0, 0) => {
+                x = 0;
+                y += 1;
+            }
+            (0, 1) => break,
+            (0, 2) => {
+                let [dx, dy] = read(data, at)?;
+                at += 2;
+                x += dx as usize;
+                y += dy as usize;
+            }
+            (0, len) => {
+                let run = data
+                    .get(at..at + len as usize)
+                    .ok_or(BmpError::Truncated)?;
+                for &value in run {
+                    put(x, y, value);
+                    x += 1;
+                }
+                // Absolute runs are padded to a whole number of words.
+                at += (len as usize).next_multiple_of(2);
+            }
+            (count, value) => {
+                for _ in 0..count {
+                    put(x, y, value);
+                    x += 1;
+                }
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ImageFormat;
+
+    /// A BMP file with a `BITMAPINFOHEADER` declared as `header_size`
+    /// bytes, followed by `extra`, which holds the rest of a longer header
+    /// and the palette or masks, and then the pixel `data`.
+    fn bmp(
+        header_size: u32,
+        [width, height]: [i32; 2],
+        bpp: u16,
+        compression: u32,
+        colors_used: u32,
+        extra: &[u8],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let data_offset = 14 + 40 + extra.len() as u32;
+
+        let mut bytes = b"BM".to_vec();
+        bytes.extend((data_offset + data.len() as u32).to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(data_offset.to_le_bytes());
+
+        bytes.extend(header_size.to_le_bytes());
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(bpp.to_le_bytes());
+        bytes.extend(compression.to_le_bytes());
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend([0; 8]);
+        bytes.extend(colors_used.to_le_bytes());
+        bytes.extend([0; 4]);
+
+        bytes.extend(extra);
+        bytes.extend(data);
+        bytes
+    }
+
+    /// A palette of `n` greys, entry `k` being `0x404040 * k`.
+    fn greys(n: u8) -> Vec<u8> {
+        (0..n)
+            .flat_map(|k| [0x40 * k, 0x40 * k, 0x40 * k, 0])
+            .collect()
+    }
+
+    /// Palette indices `0, 1, 2` then `2, 1, 0`, in 3-pixel rows padded to
+    /// 4 bytes.
+    const PADDED_ROWS: [u8; 8] = [0, 1, 2, 0xff, 2, 1, 0, 0xff];
+
+    #[test]
+    fn palettised_rows_bottom_up() {
+        let file = bmp(40, [3, 2], 8, BI_RGB, 3, &greys(3), &PADDED_ROWS);
+        let texture = decode(&file).unwrap();
+
+        assert_eq!([texture.width(), texture.height()], [3, 2]);
+        assert_eq!(
+            texture.pixels(),
+            [0x808080, 0x404040, 0, 0, 0x404040, 0x808080]
+        );
+    }
+
+    #[test]
+    fn palettised_rows_top_down() {
+        let file = bmp(40, [3, -2], 8, BI_RGB, 3, &greys(3), &PADDED_ROWS);
+
+        assert_eq!(
+            decode(&file).unwrap().pixels(),
+            [0, 0x404040, 0x808080, 0x808080, 0x404040, 0]
+        );
+    }
+
+    #[test]
+    fn rle8_escapes() {
+        let data = [
+            3, 1, // a run of three 1s
+            0, 0, // end of line
+            0, 3, 2, 3, 2, 0, // three literal pixels, padded
+            0, 2, 0, 1, // move up a row
+            1, 3, // a single 3
+            0, 1, // end of bitmap
+        ];
+        let file = bmp(40, [4, 3], 8, BI_RLE8, 4, &greys(4), &data);
+
+        // The top row first; skipped pixels are index 0.
+        assert_eq!(
+            decode(&file).unwrap().pixels(),
+            [
+                0, 0, 0, 0xc0c0c0, //
+                0x808080, 0xc0c0c0, 0x808080, 0, //
+                0x404040, 0x404040, 0x404040, 0,
+            ]
+        );
+    }
+
+    /// Masks that take red, green and blue from the second, third and last
+    /// bytes of a pixel, so they can't be mistaken for the defaults.
+    const MASKS: [u32; 3] = [0x0000_ff00, 0x00ff_0000, 0xff00_0000];
+
+    #[test]
+    fn bitfields_after_a_short_header() {
+        let masks: Vec<u8> =
+            MASKS.iter().flat_map(|m| m.to_le_bytes()).collect();
+        let file = bmp(40, [1, 1], 32, BI_BITFIELDS, 0, &masks, &[4, 3, 2, 1]);
+
+        assert_eq!(decode(&file).unwrap().pixels(), [0x030201]);
+    }
+
+    #[test]
+    fn bitfields_in_a_v4_header() {
+        let mut rest: Vec<u8> =
+            MASKS.iter().flat_map(|m| m.to_le_bytes()).collect();
+        rest.resize(108 - 40, 0);
+        let file = bmp(108, [1, 1], 32, BI_BITFIELDS, 0, &rest, &[4, 3, 2, 1]);
+
+        assert_eq!(decode(&file).unwrap().pixels(), [0x030201]);
+    }
+
+    #[test]
+    fn truncated_pixels() {
+        let file = bmp(40, [3, 2], 8, BI_RGB, 3, &greys(3), &PADDED_ROWS);
+
+        assert_eq!(decode(&file[..file.len() - 1]), Err(BmpError::Truncated));
+        assert_eq!(decode(&file[..20]), Err(BmpError::Truncated));
+    }
+
+    #[test]
+    fn zero_sized() {
+        for size in [[0, 2], [3, 0]] {
+            let file = bmp(40, size, 8, BI_RGB, 3, &greys(3), &PADDED_ROWS);
+
+            assert_eq!(
+                decode(&file),
+                Err(BmpError::InvalidDimensions {
+                    width: size[0],
+                    height: size[1],
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn index_past_the_palette() {
+        let file = bmp(40, [3, 2], 8, BI_RGB, 2, &greys(2), &PADDED_ROWS);
+
+        assert_eq!(decode(&file), Err(BmpError::InvalidPaletteIndex(2)));
+    }
+
+    #[test]
+    fn round_trip_through_the_encoder() {
+        // Three pixels a row, so the 24-bit rows need padding.
+        let pixels = [0x123456, 0xabcdef, 0x000000, 0xffffff, 0x010203, 0x7f];
+        let mut file = Vec::new();
+        ImageFormat::Bmp.encode(&mut file, 3, 2, &pixels).unwrap();
+
+        let texture = decode(&file).unwrap();
+        assert_eq!([texture.width(), texture.height()], [3, 2]);
+        assert_eq!(texture.pixels(), pixels);
+    }
+}
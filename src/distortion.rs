@@ -0,0 +1,275 @@
+//! Local distortion of a projection, measured against its generating sphere.
+
+use std::{f32::consts::PI, fmt};
+
+use crate::{
+    projection::Projection,
+    sphere::{self, Vec3},
+};
+
+/// Scale factors of a projection at a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Distortion {
+    /// Scale factor along the geographic meridian.
+    pub h: f32,
+    /// Scale factor along the geographic parallel.
+    pub k: f32,
+    /// Largest scale factor in any direction.
+    pub a: f32,
+    /// Smallest scale factor in any direction.
+    pub b: f32,
+}
+impl Distortion {
+    /// Distortion at `(lat, lon)` in the polar aspect of `projection`, where
+    /// `pole` is the geographic north pole in the same frame.
+    pub fn at(
+        projection: &dyn Projection,
+        lat: f32,
+        lon: f32,
+        pole: Vec3,
+    ) -> Option<Self> {
+        // Derivatives per unit of arc on the generating sphere.
+        let [[n_x, e_x], [n_y, e_y]] = projection.arc_jacobian(lat, lon)?;
+        let scale = projection.scale();
+        let north = [n_x / scale, n_y / scale];
+        let east = [e_x / scale, e_y / scale];
+
+        // Singular values of the arc-length Jacobian.
+        let e = north[0] * north[0] + north[1] * north[1];
+        let g = east[0] * east[0] + east[1] * east[1];
+        let f = north[0] * east[0] + north[1] * east[1];
+        let root = ((e - g) * (e - g) + 4.0 * f * f).sqrt();
+        let a = ((e + g + root) / 2.0).sqrt();
+        let b = ((e + g - root).max(0.0) / 2.0).sqrt();
+
+        // Turn the local north onto the geographic one. At the geographic
+        // poles the meridian is undefined and the local one is kept.
+        let p = sphere::to_unit(lat, lon);
+        let (sin_lat, cos_lat) = lat.sin_cos();
+        let (sin_lon, cos_lon) = lon.sin_cos();
+        let local_north = [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat];
+        let local_east = [-sin_lon, cos_lon, 0.0];
+
+        let dot = pole[0] * p[0] + pole[1] * p[1] + pole[2] * p[2];
+        let geo_north = [
+            pole[0] - dot * p[0],
+            pole[1] - dot * p[1],
+            pole[2] - dot * p[2],
+        ];
+        let (cos, sin) = (
+            geo_north[0] * local_north[0]
+                + geo_north[1] * local_north[1]
+                + geo_north[2] * local_north[2],
+            geo_north[0] * local_east[0]
+                + geo_north[1] * local_east[1]
+                + geo_north[2] * local_east[2],
+        );
+        let len = cos.hypot(sin);
+        let (cos, sin) = if len > 1e-6 {
+            (cos / len, sin / len)
+        } else {
+            (1.0, 0.0)
+        };
+
+        let h = [
+            north[0] * cos + east[0] * sin,
+            north[1] * cos + east[1] * sin,
+        ];
+        let k = [
+            east[0] * cos - north[0] * sin,
+            east[1] * cos - north[1] * sin,
+        ];
+
+        Some(Self {
+            h: h[0].hypot(h[1]),
+            k: k[0].hypot(k[1]),
+            a,
+            b,
+        })
+    }
+
+    /// Ratio of projected to true area.
+    pub fn areal(&self) -> f32 {
+        self.a * self.b
+    }
+
+    /// Maximum angular deformation, in radians.
+    pub fn angular(&self) -> f32 {
+        2.0 * ((self.a - self.b) / (self.a + self.b)).asin()
+    }
+}
+
+/// The distortion measures that can be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Metric {
+    #[default]
+    Areal,
+    Angular,
+    Meridian,
+    Parallel,
+}
+impl Metric {
+    pub const ALL: [Self; 4] =
+        [Self::Areal, Self::Angular, Self::Meridian, Self::Parallel];
+
+    /// Scale factors shown span `1/RANGE..RANGE` on a logarithmic ramp.
+    const RANGE: f32 = 4.0;
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Areal => "areal scale",
+            Self::Angular => "angular deformation",
+            Self::Meridian => "meridian scale h",
+            Self::Parallel => "parallel scale k",
+        }
+    }
+
+    /// Describes what the ends of the colour ramp stand for.
+    pub fn legend(self) -> &'static str {
+        match self {
+            Self::Angular => "0° (white) to 90° (red)",
+            _ => "1/4 (blue) to 1 (white) to 4 (red)",
+        }
+    }
+
+    pub fn value(self, distortion: &Distortion) -> f32 {
+        match self {
+            Self::Areal => distortion.areal(),
+            Self::Angular => distortion.angular(),
+            Self::Meridian => distortion.h,
+            Self::Parallel => distortion.k,
+        }
+    }
+
+    /// Position of `value` on the colour ramp, from `0` to `1`.
+    pub fn normalize(self, value: f32) -> f32 {
+        let t = match self {
+            Self::Angular => 0.5 + value / PI,
+            _ => 0.5 + value.log(Self::RANGE) / 2.0,
+        };
+
+        t.clamp(0.0, 1.0)
+    }
+
+    pub fn color(self, distortion: &Distortion) -> u32 {
+        ramp(self.normalize(self.value(distortion)))
+    }
+
+    /// Colour at position `t`, from `0` to `1`, along the legend.
+    pub fn legend_color(self, t: f32) -> u32 {
+        match self {
+            Self::Angular => ramp(0.5 + t / 2.0),
+            _ => ramp(t),
+        }
+    }
+
+    /// The metric after this one in [`Metric::ALL`], if any.
+    pub fn next(self) -> Option<Self> {
+        let idx = Self::ALL.iter().position(|&m| m == self).unwrap();
+
+        Self::ALL.get(idx + 1).copied()
+    }
+}
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A diverging blue–white–red ramp, as a `0RGB` pixel.
+pub fn ramp(t: f32) -> u32 {
+    const STOPS: [[f32; 3]; 3] = [
+        [59.0, 76.0, 192.0],
+        [221.0, 221.0, 221.0],
+        [180.0, 4.0, 38.0],
+    ];
+
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let idx = (t as usize).min(STOPS.len() - 2);
+    let frac = t - idx as f32;
+
+    let [r, g, b] = [0, 1, 2].map(|c| {
+        let (from, to) = (STOPS[idx][c], STOPS[idx + 1][c]);
+
+        (from + (to - from) * frac) as u32
+    });
+
+    (r << 16) | (g << 8) | b
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::projection::ProjectionKind;
+
+    /// Local `(lat, lon)` off the centre and away from the antipode.
+    fn points() -> impl Iterator<Item = (f32, f32)> {
+        [-60.0f32, -20.0, 10.0, 45.0, 80.0]
+            .into_iter()
+            .flat_map(|lat| {
+                [-150.0f32, -40.0, 0.0, 90.0]
+                    .map(|lon| (lat.to_radians(), lon.to_radians()))
+            })
+    }
+
+    fn distortion(kind: ProjectionKind, (lat, lon): (f32, f32)) -> Distortion {
+        Distortion::at(&*kind.with_scale(50.0), lat, lon, [0.0, 0.0, 1.0])
+            .unwrap()
+    }
+
+    fn assert_near(actual: f32, expected: f32, what: &str) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{what}: {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn lambert_azimuthal_keeps_areas() {
+        for p in points() {
+            let d = distortion(ProjectionKind::LambertAzimuthal, p);
+            assert_near(d.areal(), 1.0, &format!("areal at {p:?}"));
+        }
+    }
+
+    #[test]
+    fn stereographic_and_tangent_keep_angles() {
+        for kind in [ProjectionKind::Stereographic, ProjectionKind::Tangent] {
+            for p in points() {
+                let d = distortion(kind, p);
+                assert_near(d.angular(), 0.0, &format!("{kind} at {p:?}"));
+            }
+        }
+    }
+
+    #[test]
+    fn azimuthal_equidistant_keeps_distances_from_the_centre() {
+        for p in points() {
+            let d = distortion(ProjectionKind::AzimuthalEquidistant, p);
+            let dist = FRAC_PI_2 - p.0;
+
+            assert_near(d.h, 1.0, &format!("h at {p:?}"));
+            assert_near(d.k, dist / dist.sin(), &format!("k at {p:?}"));
+        }
+    }
+
+    #[test]
+    fn scale_factors_follow_the_geographic_north() {
+        // With the geographic pole on the local equator, the meridian
+        // through a point on the local meridian of longitude `π/2` runs
+        // along the local parallel there, so `h` and `k` trade places.
+        let projection = ProjectionKind::AzimuthalEquidistant.with_scale(50.0);
+
+        for lat in [-60.0f32, -20.0, 10.0, 45.0, 80.0].map(f32::to_radians) {
+            let d =
+                Distortion::at(&*projection, lat, FRAC_PI_2, [1.0, 0.0, 0.0])
+                    .unwrap();
+            let dist = FRAC_PI_2 - lat;
+
+            assert_near(d.h, dist / dist.sin(), &format!("h at {lat}"));
+            assert_near(d.k, 1.0, &format!("k at {lat}"));
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Writing rendered frames to image files and video streams.
+
+use std::{
+    error, fmt, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// The file name doesn't end in the extension of a supported format.
+    UnknownFormat,
+    /// The format can't store transparency.
+    NoAlpha(ImageFormat),
+    Png(png::EncodingError),
+}
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::UnknownFormat => {
+                write!(f, "unknown image format, use .png, .bmp or .ppm")
+            }
+            Self::NoAlpha(format) => {
+                let name = format.name();
+                write!(f, "{name} images can't be transparent, use .png")
+            }
+            Self::Png(e) => e.fmt(f),
+        }
+    }
+}
+impl error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::UnknownFormat | Self::NoAlpha(_) => None,
+            Self::Png(e) => Some(e),
+        }
+    }
+}
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<png::EncodingError> for SaveError {
+    fn from(e: png::EncodingError) -> Self {
+        Self::Png(e)
+    }
+}
+
+/// Formats frames can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Bmp,
+    /// Binary portable pixmap (`P6`).
+    Ppm,
+}
+impl ImageFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "PNG",
+            Self::Bmp => "BMP",
+            Self::Ppm => "PPM",
+        }
+    }
+
+    /// The format named by the extension of `path`, ignoring case.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match ext.as_str() {
+            "png" => Some(Self::Png),
+            "bmp" => Some(Self::Bmp),
+            "ppm" => Some(Self::Ppm),
+            _ => None,
+        }
+    }
+
+    /// Writes a `width`×`height` frame of `0RGB` pixels, stored row by row
+    /// from the top.
+    pub fn encode(
+        self,
+        out: impl Write,
+        width: u32,
+        height: u32,
+        pixels: &[u32],
+    ) -> Result<(), SaveError> {
+        assert_eq!(pixels.len(), width as usize * height as usize);
+
+        match self {
+            Self::Png => encode_png(out, width, height, pixels, false),
+            Self::Bmp => Ok(encode_bmp(out, width, height, pixels)?),
+            Self::Ppm => Ok(encode_ppm(out, width, height, pixels)?),
+        }
+    }
+}
+
+/// Writes a frame to `path`, in the format its extension names.
+pub fn save(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+) -> Result<(), SaveError> {
+    let path = path.as_ref();
+    let format =
+        ImageFormat::from_path(path).ok_or(SaveError::UnknownFormat)?;
+    let mut out = BufWriter::new(fs::File::create(path)?);
+
+    format.encode(&mut out, width, height, pixels)?;
+    Ok(out.flush()?)
+}
+
+/// Like [`save`], for `ARGB` pixels whose top byte is their opacity. Only
+/// PNG keeps it.
+pub fn save_with_alpha(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+) -> Result<(), SaveError> {
+    assert_eq!(pixels.len(), width as usize * height as usize);
+
+    let path = path.as_ref();
+    match ImageFormat::from_path(path).ok_or(SaveError::UnknownFormat)? {
+        ImageFormat::Png => {
+            let mut out = BufWriter::new(fs::File::create(path)?);
+
+            encode_png(&mut out, width, height, pixels, true)?;
+            Ok(out.flush()?)
+        }
+        format => Err(SaveError::NoAlpha(format)),
+    }
+}
+
+fn rgb(px: u32) -> [u8; 3] {
+    [(px >> 16) as u8, (px >> 8) as u8, px as u8]
+}
+
+fn encode_png(
+    out: impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+    alpha: bool,
+) -> Result<(), SaveError> {
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = if alpha {
+        encoder.set_color(png::ColorType::Rgba);
+        pixels
+            .iter()
+            .flat_map(|&px| {
+                let [r, g, b] = rgb(px);
+                [r, g, b, (px >> 24) as u8]
+            })
+            .collect()
+    } else {
+        encoder.set_color(png::ColorType::Rgb);
+        pixels.iter().flat_map(|&px| rgb(px)).collect()
+    };
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+
+    Ok(writer.finish()?)
+}
+
+/// Writes an uncompressed 24-bit BMP, rows stored from the bottom up.
+fn encode_bmp(
+    mut out: impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+) -> io::Result<()> {
+    const HEADERS: u32 = 14 + 40;
+
+    let stride = (3 * width).next_multiple_of(4);
+    let size = stride * height;
+
+    out.write_all(b"BM")?;
+    out.write_all(&(HEADERS + size).to_le_bytes())?;
+    out.write_all(&[0; 4])?;
+    out.write_all(&HEADERS.to_le_bytes())?;
+
+    out.write_all(&40u32.to_le_bytes())?;
+    out.write_all(&(width as i32).to_le_bytes())?;
+    out.write_all(&(height as i32).to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?;
+    out.write_all(&24u16.to_le_bytes())?;
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(&size.to_le_bytes())?;
+    // 72 DPI, and no palette.
+    out.write_all(&2835u32.to_le_bytes())?;
+    out.write_all(&2835u32.to_le_bytes())?;
+    out.write_all(&[0; 8])?;
+
+    let mut row = vec![0; stride as usize];
+    for y in (0..height as usize).rev() {
+        let line = &pixels[y * width as usize..][..width as usize];
+        for (px, bgr) in line.iter().zip(row.chunks_exact_mut(3)) {
+            let [r, g, b] = rgb(*px);
+            bgr.copy_from_slice(&[b, g, r]);
+        }
+        out.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+fn encode_ppm(
+    mut out: impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+) -> io::Result<()> {
+    write!(out, "P6\n{width} {height}\n255\n")?;
+
+    let data: Vec<u8> = pixels.iter().flat_map(|&px| rgb(px)).collect();
+    out.write_all(&data)
+}
+
+/// Writes frames as an uncompressed YUV4MPEG2 stream, which ffmpeg and
+/// other encoders read directly.
+///
+/// Colours are converted to BT.601 limited range with full resolution
+/// chroma (`C444`).
+#[derive(Debug)]
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+    /// The planes of a frame, kept to avoid allocating for each one.
+    planes: Vec<u8>,
+}
+impl<W: Write> Y4mWriter<W> {
+    /// Writes the stream header for `width`×`height` frames shown `fps`
+    /// times per second.
+    pub fn new(
+        mut out: W,
+        width: u32,
+        height: u32,
+        fps: u32,
+    ) -> io::Result<Self> {
+        writeln!(out, "YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C444")?;
+
+        Ok(Self {
+            out,
+            width,
+            height,
+            planes: Vec::new(),
+        })
+    }
+
+    /// Appends a frame of `0RGB` pixels, stored row by row from the top.
+    pub fn write_frame(&mut self, pixels: &[u32]) -> io::Result<()> {
+        let len = self.width as usize * self.height as usize;
+        assert_eq!(pixels.len(), len);
+
+        self.planes.clear();
+        self.planes.resize(3 * len, 0);
+        let (y, uv) = self.planes.split_at_mut(len);
+        let (u, v) = uv.split_at_mut(len);
+
+        for (k, &px) in pixels.iter().enumerate() {
+            let [r, g, b] = rgb(px).map(|c| c as f32);
+
+            y[k] = (16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8;
+            u[k] = (128.0 - 0.148 * r - 0.291 * g + 0.439 * b).round() as u8;
+            v[k] = (128.0 + 0.439 * r - 0.368 * g - 0.071 * b).round() as u8;
+        }
+
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&self.planes)
+    }
+
+    /// Flushes the stream and gives back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// The path of frame `n` of an image sequence, with the first `%d` in
+/// `pattern`, optionally written with a width like `%04d`, replaced by the
+/// number. Without one, the number goes before the extension.
+pub fn sequence_path(pattern: &Path, n: u32) -> PathBuf {
+    let text = pattern.to_string_lossy();
+
+    if let Some(start) = text.find('%') {
+        let rest = &text[start + 1..];
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+
+        if rest[digits..].starts_with('d') {
+            let width = rest[..digits].parse().unwrap_or(0);
+
+            return PathBuf::from(format!(
+                "{}{n:0width$}{}",
+                &text[..start],
+                &rest[digits + 1..],
+            ));
+        }
+    }
+
+    let stem = pattern.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match pattern.extension() {
+        Some(ext) => format!("{stem}{n:04}.{}", ext.to_string_lossy()),
+        None => format!("{stem}{n:04}"),
+    };
+    pattern.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbered_at_the_pattern() {
+        assert_eq!(
+            sequence_path(Path::new("f%04d.png"), 7),
+            Path::new("f0007.png")
+        );
+        assert_eq!(
+            sequence_path(Path::new("out/%d.bmp"), 12),
+            Path::new("out/12.bmp")
+        );
+    }
+
+    #[test]
+    fn numbered_before_the_extension() {
+        assert_eq!(
+            sequence_path(Path::new("out/frame.png"), 7),
+            Path::new("out/frame0007.png")
+        );
+        assert_eq!(
+            sequence_path(Path::new("frame"), 7),
+            Path::new("frame0007")
+        );
+    }
+
+    #[test]
+    fn y4m_frame_layout() {
+        let header = b"YUV4MPEG2 W3 H2 F30:1 Ip A1:1 C444\n";
+        let mut video = Y4mWriter::new(Vec::new(), 3, 2, 30).unwrap();
+        video.write_frame(&[0xffffff, 0, 0, 0, 0, 0]).unwrap();
+        let out = video.finish().unwrap();
+
+        // A marker, then full planes of Y, U and V.
+        assert_eq!(out.len(), header.len() + b"FRAME\n".len() + 3 * 6);
+        let (start, planes) = out.split_at(header.len() + 6);
+        assert_eq!(start, [&header[..], b"FRAME\n"].concat());
+        // White and black at the limits of the limited range, without
+        // colour.
+        assert_eq!(planes[..6], [235, 16, 16, 16, 16, 16]);
+        assert!(planes[6..].iter().all(|&c| c == 128));
+    }
+}
@@ -0,0 +1,203 @@
+//! Image formats a [`Texture`] can be read from, told apart by their
+//! signatures rather than by file name.
+
+use std::io::Cursor;
+
+use crate::{
+    bmp,
+    texture::{LoadError, Texture, MAX_PIXELS},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Bmp,
+    Png,
+    Jpeg,
+}
+impl Format {
+    /// Recognises the format from the first bytes of an image.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"BM") {
+            Some(Self::Bmp)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(Self::Jpeg)
+        } else {
+            None
+        }
+    }
+}
+
+/// Decodes an image in any supported format.
+pub fn decode(bytes: &[u8]) -> Result<Texture, LoadError> {
+    match Format::sniff(bytes).ok_or(LoadError::UnknownFormat)? {
+        Format::Bmp => Ok(bmp::decode(bytes)?),
+        Format::Png => decode_png(bytes),
+        Format::Jpeg => decode_jpeg(bytes),
+    }
+}
+
+/// Rejects images of more than [`MAX_PIXELS`] pixels, which the byte
+/// budgets of the decoders let through when samples are small.
+fn check_size(width: u32, height: u32) -> Result<(), LoadError> {
+    if (width as usize)
+        .checked_mul(height as usize)
+        .is_none_or(|n| n > MAX_PIXELS)
+    {
+        return Err(LoadError::TooLarge { width, height });
+    }
+    Ok(())
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+}
+
+/// Decodes a PNG image. Alpha is dropped.
+fn decode_png(bytes: &[u8]) -> Result<Texture, LoadError> {
+    let mut decoder = png::Decoder::new_with_limits(
+        Cursor::new(bytes),
+        png::Limits {
+            bytes: 4 * MAX_PIXELS,
+        },
+    );
+    // Palettes and low bit depths are expanded and 16-bit samples cut to
+    // 8 bits, so only the colour type is left to handle.
+    decoder.set_transformations(
+        png::Transformations::EXPAND | png::Transformations::STRIP_16,
+    );
+
+    let mut reader = decoder.read_info()?;
+    check_size(reader.info().width, reader.info().height)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    let data = &data[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Grayscale => {
+            data.iter().map(|&l| rgb(l, l, l)).collect()
+        }
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .map(|la| rgb(la[0], la[0], la[0]))
+            .collect(),
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .map(|c| rgb(c[0], c[1], c[2]))
+            .collect(),
+        png::ColorType::Rgba => data
+            .chunks_exact(4)
+            .map(|c| rgb(c[0], c[1], c[2]))
+            .collect(),
+        png::ColorType::Indexed => {
+            unreachable!("palettes are expanded by the decoder")
+        }
+    };
+
+    Ok(Texture::new(info.width, info.height, pixels))
+}
+
+/// Decodes a baseline or progressive JPEG image.
+fn decode_jpeg(bytes: &[u8]) -> Result<Texture, LoadError> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    decoder.set_max_decoding_buffer_size(4 * MAX_PIXELS);
+    decoder.read_info()?;
+    let info = decoder.info().expect("the header has been read");
+    check_size(info.width as u32, info.height as u32)?;
+    let data = decoder.decode()?;
+
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => {
+            data.iter().map(|&l| rgb(l, l, l)).collect()
+        }
+        // Big-endian samples; the high byte is enough for display.
+        jpeg_decoder::PixelFormat::L16 => data
+            .chunks_exact(2)
+            .map(|l| rgb(l[0], l[0], l[0]))
+            .collect(),
+        jpeg_decoder::PixelFormat::RGB24 => data
+            .chunks_exact(3)
+            .map(|c| rgb(c[0], c[1], c[2]))
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => data
+            .chunks_exact(4)
+            .map(|c| {
+                let [r, g, b] = [c[0], c[1], c[2]].map(|ink| {
+                    ((255 - ink as u32) * (255 - c[3] as u32) / 255) as u8
+                });
+                rgb(r, g, b)
+            })
+            .collect(),
+    };
+
+    Ok(Texture::new(info.width as u32, info.height as u32, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ImageFormat;
+
+    #[test]
+    fn formats_are_told_apart_by_signature() {
+        let cases: [(&[u8], Option<Format>); 7] = [
+            (b"BM\0\0", Some(Format::Bmp)),
+            (b"\x89PNG\r\n\x1a\n\0\0", Some(Format::Png)),
+            (&[0xff, 0xd8, 0xff, 0xe0], Some(Format::Jpeg)),
+            (b"GIF89a", None),
+            (b"\x89PNG\r\n", None),
+            (&[0xff, 0xd8], None),
+            (b"", None),
+        ];
+        for (bytes, format) in cases {
+            assert_eq!(Format::sniff(bytes), format, "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let pixels = [0xff0000, 0x00ff00, 0x0000ff, 0x123456, 0xffffff, 0];
+        let mut file = Vec::new();
+        ImageFormat::Png.encode(&mut file, 3, 2, &pixels).unwrap();
+        let texture = decode(&file).unwrap();
+
+        assert_eq!([texture.width(), texture.height()], [3, 2]);
+        assert_eq!(texture.pixels(), pixels);
+    }
+
+    #[test]
+    fn png_over_the_pixel_limit() {
+        // One byte per pixel keeps the image within the byte budget.
+        let mut file = Vec::new();
+        let mut encoder = png::Encoder::new(&mut file, 1 << 15, 1 << 14);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_chunk(png::chunk::IDAT, &[]).unwrap();
+        drop(writer);
+
+        assert!(matches!(
+            decode(&file),
+            Err(LoadError::TooLarge {
+                width: 32768,
+                height: 16384
+            })
+        ));
+    }
+
+    #[test]
+    fn jpeg_over_the_pixel_limit() {
+        // A greyscale frame header of 65535×65535 pixels, then the end.
+        let mut file = vec![0xff, 0xd8, 0xff, 0xc0, 0, 11, 8];
+        file.extend([0xff, 0xff, 0xff, 0xff, 1, 1, 0x11, 0]);
+        file.extend([0xff, 0xd9]);
+
+        assert!(matches!(
+            decode(&file),
+            Err(LoadError::TooLarge {
+                width: 65535,
+                height: 65535
+            })
+        ));
+    }
+}
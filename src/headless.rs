@@ -0,0 +1,646 @@
+//! The subcommands that work on files without opening a window: `render`
+//! and `animate`, which draw views, `unproject`, which maps a view back onto
+//! the globe, and `reproject`, which maps between any two projections.
+
+use std::{
+    ffi::OsString, fmt::Display, fs, io::BufWriter, num::NonZeroUsize,
+    path::PathBuf, str::FromStr,
+};
+
+use tangent_proj::{
+    animation::Animation,
+    distortion::Metric,
+    export::{self, ImageFormat, SaveError, Y4mWriter},
+    mipmap::Mipmaps,
+    parallel::{self, Pool},
+    projection::ProjectionKind,
+    raster::{PaneCache, Renderer, View},
+    reproject::{self, Azimuthal, Georef},
+    sampler::Filter,
+    sphere::Rotation,
+    supersample::{Pattern, Supersampling},
+    texture::{self, Texture},
+};
+
+/// The option naming the projection of a view.
+macro_rules! projection_option {
+    () => {
+        "
+    --projection NAME   tangent, stereographic, gnomonic, orthographic,
+                        azimuthal-equidistant or lambert-azimuthal [tangent]"
+    };
+}
+
+/// Options for the centre and scale of a view.
+macro_rules! center_options {
+    () => {
+        "
+    --lat DEG           latitude of the projection centre [0]
+    --lon DEG           longitude of the projection centre [0]
+    --roll DEG          turn of the view around its centre [0]
+    --scale PX          radius of the sphere in pixels [a quarter of the
+                        smaller side]"
+    };
+}
+
+/// Options for what a view shows.
+macro_rules! view_options {
+    () => {
+        "
+    --ssaa N            N×N samples per pixel [1]
+    --jitter            jitter the samples
+    --tissot            draw Tissot's indicatrices
+    --metric NAME       show areal, angular, meridian or parallel distortion"
+    };
+}
+
+pub const RENDER_USAGE: &str = concat!(
+    "\
+usage: tangent-proj render [TEXTURE] -o OUTPUT [options]
+
+Writes a view to OUTPUT, a .png, .bmp or .ppm file.
+
+options:",
+    projection_option!(),
+    center_options!(),
+    view_options!(),
+);
+
+pub const ANIMATE_USAGE: &str = concat!(
+    "\
+usage: tangent-proj animate KEYFRAMES [TEXTURE] -o OUTPUT [options]
+
+Renders the views between the keyframes listed in the file KEYFRAMES, one
+per line as `time lat lon roll scale [dx dy]`, with the time in seconds, the
+angles in degrees and the scale and offset in pixels. A .y4m OUTPUT gets a
+video stream; otherwise the frames go to numbered .png, .bmp or .ppm files,
+numbered at the `%04d` in OUTPUT if it has one.
+
+options:
+    --fps N             frames per second [30]",
+    projection_option!(),
+    view_options!(),
+);
+
+pub const UNPROJECT_USAGE: &str = concat!(
+    "\
+usage: tangent-proj unproject IMAGE -o OUTPUT.png [options]
+
+Resamples IMAGE, a view made as by `render` with the given options, into an
+equirectangular OUTPUT. What the image doesn't show is left transparent.
+--size is that of the output, and --projection that of the image.
+
+options:",
+    projection_option!(),
+    center_options!(),
+);
+
+pub const REPROJECT_USAGE: &str = "\
+usage: tangent-proj reproject INPUT -o OUTPUT [options]
+
+Resamples INPUT from one projection into another. A projection NAME is
+equirectangular, for the whole globe, or one of tangent, stereographic,
+gnomonic, orthographic, azimuthal-equidistant and lambert-azimuthal, placed
+by the --from-* or --to-* options as --lat, --lon, --roll and --scale place
+a view in `render`. What INPUT doesn't show is transparent in a .png OUTPUT
+and black otherwise. --size is that of the output.
+
+options:
+    --from NAME         projection of INPUT [equirectangular]
+    --from-lat DEG      latitude of its centre [0]
+    --from-lon DEG      longitude of its centre [0]
+    --from-roll DEG     turn around its centre [0]
+    --from-scale PX     radius of the sphere in pixels [a quarter of the
+                        smaller side of INPUT]
+    --to NAME           projection of OUTPUT [tangent]
+    --to-lat DEG, --to-lon DEG, --to-roll DEG, --to-scale PX
+                        the same for OUTPUT";
+
+/// Options all subcommands take, listed after their own.
+pub const COMMON_OPTIONS: &str = "
+    --size WxH          size of the image [1024x1024]
+    --filter NAME       nearest, bilinear or bicubic [bilinear]
+    --threads N         threads to render with [one per core]";
+
+/// Reads the value of the option being parsed.
+type Value<'a> = dyn FnMut() -> Result<String, String> + 'a;
+
+/// The settings both subcommands share.
+struct Options {
+    output: Option<PathBuf>,
+    size: [u32; 2],
+    projection: ProjectionKind,
+    filter: Filter,
+    threads: NonZeroUsize,
+}
+impl Options {
+    /// Reads the arguments, first offering each option to `own`, which
+    /// tells whether it took it. Returns the arguments that aren't options.
+    fn parse(
+        args: Vec<OsString>,
+        mut own: impl FnMut(&str, &mut Value<'_>) -> Result<bool, String>,
+    ) -> Result<(Self, Vec<PathBuf>), String> {
+        let mut options = Self {
+            output: None,
+            size: [1024, 1024],
+            projection: ProjectionKind::default(),
+            filter: Filter::default(),
+            threads: parallel::default_threads(),
+        };
+        let mut positional = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(arg) = arg.to_str().map(str::to_owned) else {
+                positional.push(PathBuf::from(arg));
+                continue;
+            };
+            let mut value = || {
+                args.next()
+                    .and_then(|v| v.into_string().ok())
+                    .ok_or_else(|| format!("{arg} needs a value"))
+            };
+            if own(&arg, &mut value)? {
+                continue;
+            }
+
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    options.output = Some(PathBuf::from(value()?));
+                }
+                "--size" => options.size = parse_size(&value()?)?,
+                "--projection" => {
+                    options.projection =
+                        by_name(&arg, &ProjectionKind::ALL, &value()?, |p| {
+                            p.name()
+                        })?;
+                }
+                "--filter" => {
+                    options.filter =
+                        by_name(&arg, &Filter::ALL, &value()?, |f| f.name())?;
+                }
+                "--threads" | "-j" => {
+                    options.threads = parse(&arg, &value()?)?;
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(format!("unknown option {arg}"));
+                }
+                _ => positional.push(PathBuf::from(arg)),
+            }
+        }
+
+        Ok((options, positional))
+    }
+
+    fn output(&self) -> Result<&PathBuf, String> {
+        self.output
+            .as_ref()
+            .ok_or_else(|| "no output file given, use -o".to_string())
+    }
+}
+
+/// The projection centre and scale of a view, as options give them.
+#[derive(Debug, Default)]
+struct Center {
+    /// Degrees.
+    lat: f32,
+    lon: f32,
+    roll: f32,
+    scale: Option<f32>,
+}
+impl Center {
+    /// Takes `arg` if it's one of the options for the centre.
+    fn option(
+        &mut self,
+        arg: &str,
+        value: &mut Value<'_>,
+    ) -> Result<bool, String> {
+        let mut angle = || -> Result<f32, String> {
+            let angle: f32 = parse(arg, &value()?)?;
+            if !angle.is_finite() {
+                return Err(format!("{arg} must be a finite number"));
+            }
+            Ok(angle)
+        };
+
+        match arg {
+            "--lat" => self.lat = angle()?,
+            "--lon" => self.lon = angle()?,
+            "--roll" => self.roll = angle()?,
+            "--scale" => {
+                let scale: f32 = parse(arg, &value()?)?;
+                if !(scale.is_finite() && scale > 0.0) {
+                    return Err("--scale must be positive".to_string());
+                }
+                self.scale = Some(scale);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn rotation(&self) -> Rotation {
+        Rotation::from_center(
+            self.lat.to_radians(),
+            self.lon.to_radians(),
+            self.roll.to_radians(),
+        )
+    }
+
+    /// The scale given, or a quarter of the smaller side of the image.
+    fn scale(&self, [width, height]: [u32; 2]) -> f32 {
+        self.scale.unwrap_or(width.min(height) as f32 / 4.0)
+    }
+}
+
+/// Takes `arg` if it's one of the options for what views show.
+fn view_option(
+    view: &mut View,
+    arg: &str,
+    value: &mut Value<'_>,
+) -> Result<bool, String> {
+    match arg {
+        "--ssaa" => {
+            let grid: u32 = parse(arg, &value()?)?;
+            if !(1..=Supersampling::MAX_GRID).contains(&grid) {
+                return Err(format!(
+                    "--ssaa takes 1 to {}",
+                    Supersampling::MAX_GRID
+                ));
+            }
+            view.ssaa.grid = grid;
+        }
+        "--jitter" => view.ssaa.pattern = Pattern::Jittered,
+        "--tissot" => view.tissot = true,
+        "--metric" => {
+            view.metric =
+                Some(by_name(arg, &Metric::ALL, &value()?, |m| m.name())?);
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Runs `render` with the arguments after it.
+pub fn render(args: Vec<OsString>) -> Result<(), String> {
+    let mut center = Center::default();
+    let mut view = View::default();
+
+    let (options, positional) = Options::parse(args, |arg, value| {
+        Ok(center.option(arg, value)? || view_option(&mut view, arg, value)?)
+    })?;
+    let [texture] = optional_args(&positional)?;
+    let output = options.output()?;
+    let write_error =
+        |e: &SaveError| format!("can't write {}: {e}", output.display());
+    // Better to fail now than after rendering.
+    if ImageFormat::from_path(output).is_none() {
+        return Err(write_error(&SaveError::UnknownFormat));
+    }
+    let mipmaps = load_texture(texture)?;
+
+    let [width, height] = options.size;
+    let view = View {
+        rot: center.rotation(),
+        scale: center.scale(options.size),
+        filter: options.filter,
+        ..view
+    };
+
+    let pool = Pool::new(options.threads);
+    let pixels = Renderer::new(&mipmaps, &pool).render(
+        options.size,
+        options.projection,
+        &view,
+    );
+
+    export::save(output, width, height, &pixels).map_err(|e| write_error(&e))
+}
+
+/// Runs `unproject` with the arguments after it.
+pub fn unproject(args: Vec<OsString>) -> Result<(), String> {
+    let mut center = Center::default();
+
+    let (options, positional) =
+        Options::parse(args, |arg, value| center.option(arg, value))?;
+    let [Some(image)] = optional_args(&positional)? else {
+        return Err("no image given".to_string());
+    };
+    let output = options.output()?;
+    let write_error =
+        |e: &SaveError| format!("can't write {}: {e}", output.display());
+    // Better to fail now than after resampling.
+    match ImageFormat::from_path(output) {
+        Some(ImageFormat::Png) => {}
+        Some(format) => return Err(write_error(&SaveError::NoAlpha(format))),
+        None => return Err(write_error(&SaveError::UnknownFormat)),
+    }
+    let image = load_texture(Some(image))?;
+
+    let base = image.base();
+    let source = Azimuthal {
+        projection: options.projection,
+        rot: center.rotation(),
+        scale: center.scale([base.width(), base.height()]),
+        cam_offset: [0.0, 0.0],
+    };
+    let [width, height] = options.size;
+    let pixels = reproject::reproject(
+        &image,
+        &Georef::Azimuthal(source),
+        &Georef::Equirectangular,
+        options.size,
+        options.filter,
+        &Pool::new(options.threads),
+    );
+
+    export::save_with_alpha(output, width, height, &pixels)
+        .map_err(|e| write_error(&e))
+}
+
+/// Runs `reproject` with the arguments after it.
+pub fn reproject(args: Vec<OsString>) -> Result<(), String> {
+    let mut from = Placement::default();
+    let mut to = Placement {
+        projection: Some(ProjectionKind::default()),
+        ..Placement::default()
+    };
+
+    let (options, positional) = Options::parse(args, |arg, value| {
+        if arg == "--projection" {
+            return Err("use --from and --to for reproject".to_string());
+        }
+        if let Some(arg) = arg.strip_prefix("--from") {
+            return from.option("--from", arg, value);
+        }
+        if let Some(arg) = arg.strip_prefix("--to") {
+            return to.option("--to", arg, value);
+        }
+        Ok(false)
+    })?;
+    let [Some(input)] = optional_args(&positional)? else {
+        return Err("no input given".to_string());
+    };
+    let output = options.output()?;
+    let write_error =
+        |e: &SaveError| format!("can't write {}: {e}", output.display());
+    // Better to fail now than after resampling.
+    let Some(format) = ImageFormat::from_path(output) else {
+        return Err(write_error(&SaveError::UnknownFormat));
+    };
+    let image = load_texture(Some(input))?;
+
+    let base = image.base();
+    let [width, height] = options.size;
+    let pixels = reproject::reproject(
+        &image,
+        &from.georef([base.width(), base.height()]),
+        &to.georef(options.size),
+        options.size,
+        options.filter,
+        &Pool::new(options.threads),
+    );
+
+    match format {
+        ImageFormat::Png => {
+            export::save_with_alpha(output, width, height, &pixels)
+        }
+        _ => export::save(output, width, height, &pixels),
+    }
+    .map_err(|e| write_error(&e))
+}
+
+/// One side of `reproject`: a projection, `None` for equirectangular, and
+/// where it's centred.
+#[derive(Debug, Default)]
+struct Placement {
+    projection: Option<ProjectionKind>,
+    center: Center,
+}
+impl Placement {
+    /// Takes `arg`, with `side`, its `--from` or `--to`, stripped, if it's
+    /// one of the options for this side.
+    fn option(
+        &mut self,
+        side: &str,
+        arg: &str,
+        value: &mut Value<'_>,
+    ) -> Result<bool, String> {
+        if arg.is_empty() {
+            let names = [None].into_iter().chain(ProjectionKind::ALL.map(Some));
+
+            self.projection =
+                by_name(side, &names.collect::<Vec<_>>(), &value()?, |p| {
+                    p.map_or("equirectangular", ProjectionKind::name)
+                })?;
+            return Ok(true);
+        }
+
+        if !arg.starts_with('-') {
+            return Ok(false);
+        }
+        self.center.option(&format!("-{arg}"), value)
+    }
+
+    /// How an image of `size` in this projection lies on the globe.
+    fn georef(&self, size: [u32; 2]) -> Georef {
+        match self.projection {
+            None => Georef::Equirectangular,
+            Some(projection) => Georef::Azimuthal(Azimuthal {
+                projection,
+                rot: self.center.rotation(),
+                scale: self.center.scale(size),
+                cam_offset: [0.0, 0.0],
+            }),
+        }
+    }
+}
+
+/// Runs `animate` with the arguments after it.
+pub fn animate(args: Vec<OsString>) -> Result<(), String> {
+    let mut fps = 30u32;
+    let mut view = View::default();
+
+    let (options, positional) = Options::parse(args, |arg, value| {
+        match arg {
+            "--fps" => fps = parse(arg, &value()?)?,
+            _ => return view_option(&mut view, arg, value),
+        }
+        Ok(true)
+    })?;
+    if fps == 0 {
+        return Err("--fps must be positive".to_string());
+    }
+    let Some((keyframes, rest)) = positional.split_first() else {
+        return Err("no keyframe file given".to_string());
+    };
+    let [texture] = optional_args(rest)?;
+    let output = options.output()?;
+    let write_error =
+        |e: &dyn Display| format!("can't write {}: {e}", output.display());
+
+    let animation = fs::read_to_string(keyframes)
+        .map_err(|e| e.to_string())
+        .and_then(|text| Animation::parse(&text).map_err(|e| e.to_string()))
+        .map_err(|e| format!("can't read {}: {e}", keyframes.display()))?;
+    let mipmaps = load_texture(texture)?;
+
+    let [width, height] = options.size;
+    let mut video = if output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("y4m"))
+    {
+        let file = fs::File::create(output).map_err(|e| write_error(&e))?;
+
+        Some(
+            Y4mWriter::new(BufWriter::new(file), width, height, fps)
+                .map_err(|e| write_error(&e))?,
+        )
+    } else {
+        // Better to fail now than after rendering the first frame.
+        if ImageFormat::from_path(output).is_none() {
+            return Err(write_error(&SaveError::UnknownFormat));
+        }
+
+        None
+    };
+
+    let duration = animation.end() - animation.start();
+    let frames = (duration * fps as f32).floor() as u32 + 1;
+    let pool = Pool::new(options.threads);
+    let renderer = Renderer::new(&mipmaps, &pool);
+    // Frames that only turn the globe reuse the pixel centres.
+    let mut cache = PaneCache::default();
+    let mut pixels = vec![0; width as usize * height as usize];
+
+    for n in 0..frames {
+        let key = animation.at(animation.start() + n as f32 / fps as f32);
+        let view = View {
+            rot: key.rot,
+            scale: key.scale,
+            cam_offset: key.cam_offset,
+            filter: options.filter,
+            ..view
+        };
+        renderer.draw_pane(
+            &mut pixels,
+            options.size,
+            0..width,
+            options.projection,
+            &view,
+            &mut cache,
+        );
+
+        match &mut video {
+            Some(video) => {
+                video.write_frame(&pixels).map_err(|e| write_error(&e))?;
+            }
+            None => {
+                let path = export::sequence_path(output, n);
+                export::save(&path, width, height, &pixels).map_err(|e| {
+                    format!("can't write {}: {e}", path.display())
+                })?;
+            }
+        }
+        eprint!("\rframe {}/{frames}", n + 1);
+    }
+    eprintln!();
+
+    if let Some(video) = video {
+        video.finish().map_err(|e| write_error(&e))?;
+    }
+    Ok(())
+}
+
+/// Loads the texture at `path`, or makes the graticule without one.
+fn load_texture(path: Option<&PathBuf>) -> Result<Mipmaps, String> {
+    let texture = match path {
+        Some(path) => Texture::load(path)
+            .map_err(|e| format!("can't load {}: {e}", path.display()))?,
+        None => Texture::graticule(1440, 720),
+    };
+
+    Ok(Mipmaps::new(texture))
+}
+
+/// Up to `N` arguments, each of which may be missing.
+fn optional_args<const N: usize>(
+    args: &[PathBuf],
+) -> Result<[Option<&PathBuf>; N], String> {
+    if let Some(extra) = args.get(N) {
+        return Err(format!("unexpected argument {}", extra.display()));
+    }
+
+    Ok(std::array::from_fn(|k| args.get(k)))
+}
+
+fn parse<T>(arg: &str, value: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value {value} for {arg}: {e}"))
+}
+
+/// Parses `WxH`, with both sides at least one pixel and no more pixels than
+/// a texture may have.
+fn parse_size(value: &str) -> Result<[u32; 2], String> {
+    let invalid = || format!("invalid size {value}, expected e.g. 1024x768");
+    let (w, h) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let size = [
+        w.parse().map_err(|_| invalid())?,
+        h.parse().map_err(|_| invalid())?,
+    ];
+
+    if size.contains(&0)
+        || size[0] as usize * size[1] as usize > texture::MAX_PIXELS
+    {
+        return Err(invalid());
+    }
+    Ok(size)
+}
+
+/// The item `value` names for the option `arg`: the one whose name, with
+/// dashes for spaces, is `value`, or else the only one starting with it.
+fn by_name<T: Copy>(
+    arg: &str,
+    items: &[T],
+    value: &str,
+    name: impl Fn(T) -> &'static str,
+) -> Result<T, String> {
+    let value = value.to_ascii_lowercase();
+    let name = |&item: &T| name(item).replace(' ', "-");
+
+    if let Some(item) = items.iter().find(|item| name(item) == value) {
+        return Ok(*item);
+    }
+    let matches: Vec<_> = items
+        .iter()
+        .filter(|item| !value.is_empty() && name(item).starts_with(&value))
+        .collect();
+
+    match matches[..] {
+        [item] => Ok(*item),
+        [] => Err(format!(
+            "invalid value {value} for {arg}, expected {}",
+            one_of(items.iter().map(name))
+        )),
+        _ => Err(format!(
+            "ambiguous value {value} for {arg}, could be {}",
+            one_of(matches.into_iter().map(name))
+        )),
+    }
+}
+
+/// Lists names as `a, b or c`.
+fn one_of(names: impl Iterator<Item = String>) -> String {
+    let names: Vec<_> = names.collect();
+
+    match names.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
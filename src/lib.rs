@@ -0,0 +1,15 @@
+pub mod animation;
+pub mod bmp;
+pub mod distortion;
+pub mod export;
+pub mod format;
+pub mod mipmap;
+pub mod parallel;
+pub mod projection;
+pub mod raster;
+pub mod reproject;
+pub mod sampler;
+pub mod sphere;
+pub mod supersample;
+pub mod texture;
+pub mod tissot;
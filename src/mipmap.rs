@@ -0,0 +1,69 @@
+//! Pyramids of successively halved textures, for sampling without aliasing
+//! where many texels fall into one pixel.
+
+use crate::texture::Texture;
+
+/// A texture together with its downsampled levels. Level `0` is the texture
+/// itself and every further level halves both dimensions, rounding up, down
+/// to a single texel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mipmaps {
+    levels: Vec<Texture>,
+}
+impl Mipmaps {
+    pub fn new(texture: Texture) -> Self {
+        let mut levels = vec![texture];
+
+        while let Some(last) = levels.last() {
+            if last.width() == 1 && last.height() == 1 {
+                break;
+            }
+            levels.push(downsample(last));
+        }
+
+        Self { levels }
+    }
+
+    /// The full resolution texture.
+    pub fn base(&self) -> &Texture {
+        &self.levels[0]
+    }
+
+    /// The given level, or the coarsest one if there are fewer.
+    pub fn level(&self, level: usize) -> &Texture {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+}
+
+/// Averages blocks of 2×2 texels. An odd last row or column is averaged
+/// with itself.
+fn downsample(texture: &Texture) -> Texture {
+    let (w, h) = (texture.width(), texture.height());
+    let (half_w, half_h) = (w.div_ceil(2), h.div_ceil(2));
+
+    let pixels = (0..half_h)
+        .flat_map(|y| (0..half_w).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (x0, y0) = (2 * x, 2 * y);
+            let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+            let texels = [
+                texture.texel(x0, y0),
+                texture.texel(x1, y0),
+                texture.texel(x0, y1),
+                texture.texel(x1, y1),
+            ];
+
+            [16, 8, 0].iter().fold(0, |px, shift| {
+                let sum: u32 = texels.iter().map(|t| (t >> shift) & 0xff).sum();
+
+                (px << 8) | ((sum + 2) / 4)
+            })
+        })
+        .collect();
+
+    Texture::new(half_w, half_h, pixels)
+}
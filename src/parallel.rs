@@ -0,0 +1,233 @@
+//! Splitting the rows of a frame between threads.
+
+use std::{
+    fmt, mem,
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, MutexGuard, TryLockError},
+    thread::{self, JoinHandle},
+};
+
+/// Rows handed to a thread at a time. Bands are small so that threads which
+/// get cheap rows, such as those off the projection, go on to take more.
+const BAND: usize = 8;
+
+/// The number of threads to render with when none is given: one per core.
+pub fn default_threads() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// A job borrowed from the stack of [`Pool::run`], which waits for the
+/// workers to be done with it before it returns.
+type Job = &'static (dyn Fn() + Sync);
+
+#[derive(Default)]
+struct State {
+    job: Option<Job>,
+    /// Counts the jobs handed out, so that each worker runs each one once.
+    generation: u64,
+    /// Workers yet to finish the current job.
+    busy: usize,
+    panicked: bool,
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    start: Condvar,
+    done: Condvar,
+}
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // Nothing panics with the lock held, so it can't be poisoned.
+        self.state.lock().unwrap()
+    }
+
+    fn work(&self) {
+        let mut seen = 0;
+        loop {
+            let job = {
+                let mut state = self.lock();
+                while state.generation == seen && !state.shutdown {
+                    state = self.start.wait(state).unwrap();
+                }
+                if state.shutdown {
+                    return;
+                }
+                seen = state.generation;
+                state.job.expect("a job comes with each generation")
+            };
+
+            let result = panic::catch_unwind(AssertUnwindSafe(job));
+
+            let mut state = self.lock();
+            state.panicked |= result.is_err();
+            state.busy -= 1;
+            if state.busy == 0 {
+                self.done.notify_all();
+            }
+        }
+    }
+}
+
+/// Threads that stay up between frames, so that drawing one doesn't start
+/// and join a thread per core.
+pub struct Pool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+    /// Held while the workers are on a job.
+    turn: Mutex<()>,
+}
+impl Pool {
+    /// Starts a pool that works with `threads` threads, the one calling
+    /// [`Pool::run`] among them.
+    pub fn new(threads: NonZeroUsize) -> Self {
+        let shared = Arc::new(Shared::default());
+        let workers = (1..threads.get())
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || shared.work())
+            })
+            .collect();
+
+        Self {
+            shared,
+            workers,
+            turn: Mutex::new(()),
+        }
+    }
+
+    /// The number of threads jobs run on.
+    pub fn threads(&self) -> NonZeroUsize {
+        NonZeroUsize::MIN.saturating_add(self.workers.len())
+    }
+
+    /// Calls `job` on every thread of the pool at once and returns when all
+    /// calls have. If the pool is already busy, as when called from a job,
+    /// `job` is only called on this thread.
+    pub fn run(&self, job: &(dyn Fn() + Sync)) {
+        let _turn = match self.turn.try_lock() {
+            Ok(turn) => turn,
+            // The last job panicked, which leaves the workers idle.
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return job(),
+        };
+        if self.workers.is_empty() {
+            return job();
+        }
+
+        // SAFETY: the workers only call the job between here and the wait
+        // for `busy` to reach zero below, which happens even if `job`
+        // panics, so the borrow outlives every use.
+        let job = unsafe { mem::transmute::<&(dyn Fn() + Sync), Job>(job) };
+        {
+            let mut state = self.shared.lock();
+            state.job = Some(job);
+            state.generation += 1;
+            state.busy = self.workers.len();
+            self.shared.start.notify_all();
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(job));
+
+        let mut state = self.shared.lock();
+        while state.busy > 0 {
+            state = self.shared.done.wait(state).unwrap();
+        }
+        state.job = None;
+        let panicked = mem::take(&mut state.panicked);
+        drop(state);
+
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
+        }
+        assert!(!panicked, "a worker thread panicked");
+    }
+
+    /// Calls `f` on bands of consecutive rows of a frame `width` pixels
+    /// wide, spread over the threads of the pool. `f` gets the index of the
+    /// first row of the band and its pixels.
+    pub fn for_each_band<T, F>(&self, buf: &mut [T], width: usize, f: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut [T]) + Sync,
+    {
+        if width == 0 {
+            return;
+        }
+        let bands = Mutex::new(buf.chunks_mut(BAND * width).enumerate());
+
+        self.run(&|| loop {
+            // The lock is released before the band is drawn.
+            let next = bands.lock().unwrap().next();
+            let Some((n, band)) = next else {
+                break;
+            };
+            f(n * BAND, band);
+        });
+    }
+}
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.start.notify_all();
+        for worker in self.workers.drain(..) {
+            // Panics in jobs are caught, so workers exit cleanly.
+            let _ = worker.join();
+        }
+    }
+}
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("threads", &self.threads())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_row_is_drawn_once_per_frame() {
+        let pool = Pool::new(NonZeroUsize::new(4).unwrap());
+        let (width, height) = (3, 50);
+
+        for frame in 1..4 {
+            let mut buf = vec![0; width * height];
+            pool.for_each_band(&mut buf, width, |first, band| {
+                for (r, row) in band.chunks_mut(width).enumerate() {
+                    row.fill(frame * (first + r + 1));
+                }
+            });
+
+            let rows: Vec<usize> =
+                buf.chunks(width).map(|row| row[0]).collect();
+            let expected: Vec<usize> =
+                (1..=height).map(|j| frame * j).collect();
+            assert_eq!(rows, expected);
+        }
+    }
+
+    #[test]
+    fn a_panic_reaches_the_caller_and_spares_the_pool() {
+        let pool = Pool::new(NonZeroUsize::new(3).unwrap());
+        let mut buf = vec![0; 64];
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.for_each_band(&mut buf, 1, |first, _| {
+                assert_ne!(first, 32, "bad band");
+            });
+        }));
+        assert!(result.is_err());
+
+        pool.for_each_band(&mut buf, 1, |first, band| {
+            for (r, px) in band.iter_mut().enumerate() {
+                *px = first + r + 1;
+            }
+        });
+        assert_eq!(buf, (1..=64).collect::<Vec<_>>());
+    }
+}
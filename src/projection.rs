@@ -0,0 +1,378 @@
+//! Azimuthal projections of the sphere onto a plane.
+//!
+//! Coordinates on the sphere are given in the polar aspect: latitude `π/2`
+//! is the projection centre and longitude is the azimuth around it. Plane
+//! coordinates have their origin at the centre, `x` to the right and `y` up;
+//! the meridian of longitude `0` points right and longitude grows
+//! counter-clockwise, so that the globe is seen from outside as on a map.
+//! Every projection is scaled by the radius of its generating sphere,
+//! `scale`, in plane units.
+
+use std::{
+    f32::consts::{FRAC_PI_2, PI},
+    fmt,
+};
+
+use crate::sphere;
+
+pub trait Projection {
+    /// Maps `(lat, lon)` to plane coordinates `(x, y)`, or `None` if the
+    /// point has no finite image.
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)>;
+
+    /// Maps plane coordinates `(x, y)` back to `(lat, lon)`, or `None` if
+    /// the point lies outside the image of the sphere.
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)>;
+
+    /// Largest angular distance from the centre that is projected.
+    fn domain(&self) -> f32;
+
+    /// Radius of the generating sphere, in plane units.
+    fn scale(&self) -> f32;
+
+    /// Partial derivatives of [`Projection::forward`] at `(lat, lon)`, as
+    /// the columns `[∂/∂lat, ∂/∂lon]` of `(x, y)`.
+    ///
+    /// The default implementation takes central differences.
+    fn jacobian(&self, lat: f32, lon: f32) -> Option<[[f32; 2]; 2]> {
+        const STEP: f32 = 1e-3;
+
+        let (n_x, n_y) = self.forward(lat + STEP, lon)?;
+        let (s_x, s_y) = self.forward(lat - STEP, lon)?;
+        let (e_x, e_y) = self.forward(lat, lon + STEP)?;
+        let (w_x, w_y) = self.forward(lat, lon - STEP)?;
+
+        Some([
+            [(n_x - s_x) / (2.0 * STEP), (e_x - w_x) / (2.0 * STEP)],
+            [(n_y - s_y) / (2.0 * STEP), (e_y - w_y) / (2.0 * STEP)],
+        ])
+    }
+
+    /// Derivatives of [`Projection::forward`] per radian of arc along the
+    /// local north and east at `(lat, lon)`, as the columns `[north, east]`
+    /// of `(x, y)`.
+    ///
+    /// The meridians converge at the centre, where the east derivative
+    /// vanishes; what is measured from it is continuous there, so it's
+    /// sampled just beside.
+    fn arc_jacobian(&self, lat: f32, lon: f32) -> Option<[[f32; 2]; 2]> {
+        let lat = lat.clamp(-FRAC_PI_2 + 1e-3, FRAC_PI_2 - 1e-3);
+        let [[n_x, e_x], [n_y, e_y]] = self.jacobian(lat, lon)?;
+        let east = 1.0 / lat.cos();
+
+        Some([[n_x, e_x * east], [n_y, e_y * east]])
+    }
+}
+
+/// Arc on the sphere, in radians, spanned by a unit step in the plane from
+/// `(x, y)`: the larger of the steps right and down. Zero if either step
+/// leaves the image of the sphere.
+pub fn footprint(projection: &dyn Projection, x: f32, y: f32) -> f32 {
+    let unit = |x, y| {
+        let (lat, lon) = projection.inverse(x, y)?;
+        Some(sphere::to_unit(lat, lon))
+    };
+    let (Some(p), Some(right), Some(down)) =
+        (unit(x, y), unit(x + 1.0, y), unit(x, y - 1.0))
+    else {
+        return 0.0;
+    };
+
+    // The chord is as good as the arc at the scale of a pixel.
+    let dist = |q: [f32; 3]| {
+        ((q[0] - p[0]).powi(2) + (q[1] - p[1]).powi(2) + (q[2] - p[2]).powi(2))
+            .sqrt()
+    };
+
+    dist(right).max(dist(down))
+}
+
+/// Angular distance of latitude `lat` from the centre. A latitude rounded
+/// past `π/2` is at the centre.
+fn distance(lat: f32) -> f32 {
+    (FRAC_PI_2 - lat).max(0.0)
+}
+
+/// Plane coordinates at radius `rho` along the meridian `lon`, or `None` if
+/// `rho` isn't finite or is negative, as the tangent of an angle rounded
+/// past `π/2` is at the antipode.
+fn polar(rho: f32, lon: f32) -> Option<(f32, f32)> {
+    (rho.is_finite() && rho >= 0.0).then(|| (rho * lon.cos(), rho * lon.sin()))
+}
+
+/// Radius and meridian of plane coordinates `(x, y)`.
+fn unpolar(x: f32, y: f32) -> (f32, f32) {
+    ((x * x + y * y).sqrt(), y.atan2(x))
+}
+
+/// The tangent projection.
+///
+/// A sphere rests on the plane, touching it at the projection centre. A
+/// point on the sphere is mapped to the intersection of its tangent line (in
+/// the slice of fixed azimuth) with the plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TangentProjection {
+    pub scale: f32,
+}
+impl TangentProjection {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+impl Projection for TangentProjection {
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)> {
+        let dist = distance(lat);
+
+        polar(self.scale * (dist / 2.0).tan(), lon)
+    }
+
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (rho, lon) = unpolar(x, y);
+
+        Some((FRAC_PI_2 - 2.0 * rho.atan2(self.scale), lon))
+    }
+
+    fn domain(&self) -> f32 {
+        PI
+    }
+
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+/// The stereographic projection, from the antipode of the centre onto the
+/// plane tangent at the centre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereographic {
+    pub scale: f32,
+}
+impl Stereographic {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+impl Projection for Stereographic {
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)> {
+        let dist = distance(lat);
+
+        polar(2.0 * self.scale * (dist / 2.0).tan(), lon)
+    }
+
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (rho, lon) = unpolar(x, y);
+
+        Some((FRAC_PI_2 - 2.0 * rho.atan2(2.0 * self.scale), lon))
+    }
+
+    fn domain(&self) -> f32 {
+        PI
+    }
+
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+/// The gnomonic projection, from the centre of the sphere. Only the
+/// hemisphere around the centre is projected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gnomonic {
+    pub scale: f32,
+}
+impl Gnomonic {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+impl Projection for Gnomonic {
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)> {
+        if lat <= 0.0 {
+            return None;
+        }
+        let dist = distance(lat);
+
+        polar(self.scale * dist.tan(), lon)
+    }
+
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (rho, lon) = unpolar(x, y);
+
+        Some((FRAC_PI_2 - rho.atan2(self.scale), lon))
+    }
+
+    fn domain(&self) -> f32 {
+        FRAC_PI_2
+    }
+
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+/// The orthographic projection, a parallel view of the hemisphere around
+/// the centre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orthographic {
+    pub scale: f32,
+}
+impl Orthographic {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+impl Projection for Orthographic {
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)> {
+        if lat < 0.0 {
+            return None;
+        }
+
+        polar(self.scale * distance(lat).sin(), lon)
+    }
+
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (rho, lon) = unpolar(x, y);
+        if rho > self.scale {
+            return None;
+        }
+
+        Some(((rho / self.scale).acos(), lon))
+    }
+
+    fn domain(&self) -> f32 {
+        FRAC_PI_2
+    }
+
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+/// The azimuthal equidistant projection, which preserves distances from the
+/// centre.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AzimuthalEquidistant {
+    pub scale: f32,
+}
+impl AzimuthalEquidistant {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+impl Projection for AzimuthalEquidistant {
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)> {
+        let dist = distance(lat);
+
+        polar(self.scale * dist, lon)
+    }
+
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (rho, lon) = unpolar(x, y);
+        if rho > PI * self.scale {
+            return None;
+        }
+
+        Some((FRAC_PI_2 - rho / self.scale, lon))
+    }
+
+    fn domain(&self) -> f32 {
+        PI
+    }
+
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+/// The Lambert azimuthal equal-area projection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LambertAzimuthal {
+    pub scale: f32,
+}
+impl LambertAzimuthal {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+impl Projection for LambertAzimuthal {
+    fn forward(&self, lat: f32, lon: f32) -> Option<(f32, f32)> {
+        let dist = distance(lat);
+
+        polar(2.0 * self.scale * (dist / 2.0).sin(), lon)
+    }
+
+    fn inverse(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (rho, lon) = unpolar(x, y);
+        if rho > 2.0 * self.scale {
+            return None;
+        }
+
+        Some((FRAC_PI_2 - 2.0 * (rho / (2.0 * self.scale)).asin(), lon))
+    }
+
+    fn domain(&self) -> f32 {
+        PI
+    }
+
+    fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+/// The projections that can be chosen at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ProjectionKind {
+    #[default]
+    Tangent,
+    Stereographic,
+    Gnomonic,
+    Orthographic,
+    AzimuthalEquidistant,
+    LambertAzimuthal,
+}
+impl ProjectionKind {
+    pub const ALL: [Self; 6] = [
+        Self::Tangent,
+        Self::Stereographic,
+        Self::Gnomonic,
+        Self::Orthographic,
+        Self::AzimuthalEquidistant,
+        Self::LambertAzimuthal,
+    ];
+
+    pub fn with_scale(self, scale: f32) -> Box<dyn Projection + Send + Sync> {
+        match self {
+            Self::Tangent => Box::new(TangentProjection::new(scale)),
+            Self::Stereographic => Box::new(Stereographic::new(scale)),
+            Self::Gnomonic => Box::new(Gnomonic::new(scale)),
+            Self::Orthographic => Box::new(Orthographic::new(scale)),
+            Self::AzimuthalEquidistant => {
+                Box::new(AzimuthalEquidistant::new(scale))
+            }
+            Self::LambertAzimuthal => Box::new(LambertAzimuthal::new(scale)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tangent => "tangent",
+            Self::Stereographic => "stereographic",
+            Self::Gnomonic => "gnomonic",
+            Self::Orthographic => "orthographic",
+            Self::AzimuthalEquidistant => "azimuthal equidistant",
+            Self::LambertAzimuthal => "lambert azimuthal equal-area",
+        }
+    }
+
+    /// The next projection in [`ProjectionKind::ALL`], wrapping around.
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&k| k == self).unwrap();
+
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+impl fmt::Display for ProjectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
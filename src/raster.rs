@@ -0,0 +1,367 @@
+//! Rendering views of the textured sphere into frames of `0RGB` pixels.
+
+use std::ops::Range;
+
+use crate::{
+    distortion::{Distortion, Metric},
+    mipmap::Mipmaps,
+    parallel::Pool,
+    projection::{self, Projection, ProjectionKind},
+    sampler::{Filter, Sampler},
+    sphere::{self, Matrix, Rotation, Vec3},
+    supersample::{LinearAverage, Supersampling},
+    tissot::{self, Indicatrix},
+};
+
+/// Side of the blocks of pixels that share a colour in coarse frames.
+pub const COARSE_STEP: usize = 4;
+
+/// Everything that decides what a pane shows, apart from its projection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    /// Takes the frame of the projection to the geographic one.
+    pub rot: Rotation,
+    /// Radius of the generating sphere, in pixels.
+    pub scale: f32,
+    /// Shift of the projection centre from the middle of the pane, in
+    /// pixels to the right and down.
+    pub cam_offset: [f32; 2],
+    /// Whether Tissot's indicatrices are drawn over the map.
+    pub tissot: bool,
+    /// Distortion shown instead of the texture, if any.
+    pub metric: Option<Metric>,
+    pub filter: Filter,
+    pub ssaa: Supersampling,
+}
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            rot: Rotation::IDENTITY,
+            scale: 1.0,
+            cam_offset: [0.0, 0.0],
+            tissot: false,
+            metric: None,
+            filter: Filter::default(),
+            ssaa: Supersampling::OFF,
+        }
+    }
+}
+
+/// A pixel centre of a pane on the sphere, before the view is rotated.
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    unit: Vec3,
+    /// Arc spanned by the pixel, as given by [`projection::footprint`].
+    footprint: f32,
+}
+
+/// What the pixels of a pane show, which stays the same while the view only
+/// rotates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PaneKey {
+    cols: [u32; 2],
+    height: u32,
+    projection: ProjectionKind,
+    scale: f32,
+    cam_offset: [f32; 2],
+}
+
+/// The pixel centres of a pane on the sphere, kept between frames so that
+/// rotations and changes of what is shown skip the inverse projection.
+#[derive(Debug, Default)]
+pub struct PaneCache {
+    key: Option<PaneKey>,
+    /// Row by row, for the columns of the pane.
+    pixels: Vec<Option<Local>>,
+}
+impl PaneCache {
+    /// The pixel centres for `key`, recomputed with `local` at pixel
+    /// `(i, j)` of the frame if the last frame had another key.
+    fn get(
+        &mut self,
+        key: PaneKey,
+        pool: &Pool,
+        local: impl Fn(u32, u32) -> Option<Local> + Sync,
+    ) -> &[Option<Local>] {
+        if self.key != Some(key) {
+            let [from, to] = key.cols;
+            let cols = (to - from) as usize;
+
+            self.pixels.clear();
+            self.pixels.resize(cols * key.height as usize, None);
+            pool.for_each_band(&mut self.pixels, cols, |first, band| {
+                for (j, row) in band.chunks_mut(cols).enumerate() {
+                    let j = (first + j) as u32;
+
+                    for (i, px) in (from..to).zip(row) {
+                        *px = local(i, j);
+                    }
+                }
+            });
+            self.key = Some(key);
+        }
+
+        &self.pixels
+    }
+}
+
+/// Draws views of a texture.
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer<'a> {
+    texture: &'a Mipmaps,
+    pool: &'a Pool,
+    coarse: bool,
+}
+impl<'a> Renderer<'a> {
+    /// A renderer that draws `texture` with the threads of `pool`.
+    pub fn new(texture: &'a Mipmaps, pool: &'a Pool) -> Self {
+        Self {
+            texture,
+            pool,
+            coarse: false,
+        }
+    }
+
+    /// Shades only one pixel in each block of [`COARSE_STEP`]×`COARSE_STEP`,
+    /// for frames that have to keep up with input. Coarse frames skip
+    /// supersampling and don't use the cache.
+    pub fn with_coarse(self, coarse: bool) -> Self {
+        Self { coarse, ..self }
+    }
+
+    /// Renders `view` through `projection` into a new `width`×`height`
+    /// frame.
+    pub fn render(
+        &self,
+        [width, height]: [u32; 2],
+        projection: ProjectionKind,
+        view: &View,
+    ) -> Vec<u32> {
+        let mut buf = vec![0; width as usize * height as usize];
+        self.draw_pane(
+            &mut buf,
+            [width, height],
+            0..width,
+            projection,
+            view,
+            &mut PaneCache::default(),
+        );
+
+        buf
+    }
+
+    /// Renders `view` through `projection` into the columns `cols` of a
+    /// `width`×`height` frame, centred on the middle of those columns.
+    pub fn draw_pane(
+        &self,
+        buf: &mut [u32],
+        [width, height]: [u32; 2],
+        cols: Range<u32>,
+        projection: ProjectionKind,
+        view: &View,
+        cache: &mut PaneCache,
+    ) {
+        let key = PaneKey {
+            cols: [cols.start, cols.end],
+            height,
+            projection,
+            scale: view.scale,
+            cam_offset: view.cam_offset,
+        };
+        let pane = Pane {
+            projection: projection.with_scale(view.scale),
+            origin: [
+                (cols.start + cols.end) as f32 / 2.0 + view.cam_offset[0],
+                height as f32 / 2.0 + view.cam_offset[1],
+            ],
+            rot: view.rot.matrix(),
+            pole: view.rot.inverse().rotate([0.0, 0.0, 1.0]),
+            sampler: Sampler::new(self.texture, view.filter),
+            metric: view.metric,
+            ssaa: view.ssaa,
+        };
+        let w = width as usize;
+        let (from, to) = (cols.start, cols.end);
+
+        if self.coarse {
+            // The view keeps moving, so the cache would be refilled every
+            // frame; shade each block anew.
+            let half = (COARSE_STEP - 1) as f32 / 2.0;
+
+            self.pool.for_each_band(buf, w, |first, band| {
+                let cols = from as usize..to as usize;
+
+                for r in 0..band.len() / w {
+                    let j = first + r;
+                    if r > 0 && j % COARSE_STEP != 0 {
+                        band.copy_within(
+                            (r - 1) * w + cols.start..(r - 1) * w + cols.end,
+                            r * w + cols.start,
+                        );
+                        continue;
+                    }
+
+                    let j0 = j - j % COARSE_STEP;
+                    let row = &mut band[r * w..][cols.clone()];
+                    for (k, block) in row.chunks_mut(COARSE_STEP).enumerate() {
+                        let i0 = from as usize + k * COARSE_STEP;
+                        let point =
+                            pane.local(i0 as f32 + half, j0 as f32 + half);
+
+                        block.fill(pane.shade(point, COARSE_STEP as f32));
+                    }
+                }
+            });
+        } else {
+            let pixels = cache
+                .get(key, self.pool, |i, j| pane.local(i as f32, j as f32));
+            let cols = (to - from) as usize;
+
+            self.pool.for_each_band(buf, w, |first, band| {
+                for (j, row) in band.chunks_mut(w).enumerate() {
+                    let j = first + j;
+                    let cached = &pixels[j * cols..(j + 1) * cols];
+
+                    for (i, &point) in (from..to).zip(cached) {
+                        row[i as usize] = match pane.ssaa.samples() {
+                            1 => pane.shade(point, 1.0),
+                            _ => pane.supersample(i, j as u32),
+                        };
+                    }
+                }
+            });
+        }
+
+        if view.tissot {
+            draw_tissot(
+                buf,
+                [width, height],
+                cols.clone(),
+                pane.origin,
+                &tissot::lattice(&*pane.projection, &view.rot),
+            );
+        }
+        if let Some(metric) = view.metric {
+            draw_legend(buf, [width, height], cols, metric);
+        }
+    }
+}
+
+/// What shading the pixels of one pane needs.
+struct Pane<'a> {
+    projection: Box<dyn Projection + Send + Sync>,
+    /// Pixel at which the plane origin lies.
+    origin: [f32; 2],
+    rot: Matrix,
+    /// The geographic north pole in the frame of the projection.
+    pole: Vec3,
+    sampler: Sampler<'a>,
+    metric: Option<Metric>,
+    ssaa: Supersampling,
+}
+impl Pane<'_> {
+    /// Where the point at pixel `(i, j)` of the frame lies on the sphere.
+    fn local(&self, i: f32, j: f32) -> Option<Local> {
+        let (x, y) = (i - self.origin[0], self.origin[1] - j);
+        let (lat, lon) = self.projection.inverse(x, y)?;
+
+        Some(Local {
+            unit: sphere::to_unit(lat, lon),
+            footprint: projection::footprint(&*self.projection, x, y),
+        })
+    }
+
+    /// Colour of a point of the sphere, for a sample `size` pixels across.
+    /// Points off the projection are black.
+    fn shade(&self, point: Option<Local>, size: f32) -> u32 {
+        let Some(Local { unit, footprint }) = point else {
+            return 0;
+        };
+        if let Some(metric) = self.metric {
+            let (lat, lon) = sphere::from_unit(unit);
+
+            return Distortion::at(&*self.projection, lat, lon, self.pole)
+                .map_or(0, |d| metric.color(&d));
+        }
+
+        let (lat, lon) = sphere::from_unit(self.rot.apply(unit));
+
+        self.sampler.sample(lat, lon, footprint * size)
+    }
+
+    /// Average colour of the samples of pixel `(i, j)`.
+    fn supersample(&self, i: u32, j: u32) -> u32 {
+        let size = 1.0 / self.ssaa.grid as f32;
+        let mut avg = LinearAverage::default();
+        for [dx, dy] in self.ssaa.offsets(i, j) {
+            let point = self.local(i as f32 + dx, j as f32 + dy);
+            avg.add(self.shade(point, size));
+        }
+
+        avg.color()
+    }
+}
+
+/// Blends `indicatrices` into the columns `cols` of a `width`×`height` frame
+/// whose plane origin lies at pixel `origin`.
+fn draw_tissot(
+    buf: &mut [u32],
+    [width, height]: [u32; 2],
+    cols: Range<u32>,
+    origin: [f32; 2],
+    indicatrices: &[Indicatrix],
+) {
+    const COLOR: u32 = 0xff0000;
+
+    for ind in indicatrices {
+        let [ext_x, ext_y] = ind.extent();
+        // Near the singularities the circles blow up to cover the whole
+        // view, which hides the map instead of explaining it.
+        if ext_x.max(ext_y) > width.max(height) as f32 {
+            continue;
+        }
+
+        let [ci, cj] = [origin[0] + ind.center[0], origin[1] - ind.center[1]];
+        let i0 = (ci - ext_x).floor().max(cols.start as f32) as u32;
+        let i1 = (ci + ext_x).ceil().min(cols.end as f32) as u32;
+        let j0 = (cj - ext_y).floor().max(0.0) as u32;
+        let j1 = (cj + ext_y).ceil().min(height as f32) as u32;
+
+        for i in i0..i1 {
+            for j in j0..j1 {
+                let x = i as f32 - origin[0];
+                let y = origin[1] - j as f32;
+
+                if ind.contains(x, y) {
+                    let px = &mut buf[(j * width + i) as usize];
+                    *px = ((*px & 0xfefefe) >> 1) + ((COLOR & 0xfefefe) >> 1);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the colour ramp of the distortion metrics along the bottom of the
+/// columns `cols` of a `width`×`height` frame.
+fn draw_legend(
+    buf: &mut [u32],
+    [width, height]: [u32; 2],
+    cols: Range<u32>,
+    metric: Metric,
+) {
+    const MARGIN: u32 = 16;
+    const HEIGHT: u32 = 8;
+
+    if cols.len() as u32 <= 2 * MARGIN || height <= MARGIN + HEIGHT {
+        return;
+    }
+    let (from, to) = (cols.start + MARGIN, cols.end - MARGIN);
+
+    for i in from..to {
+        let color = metric.legend_color((i - from) as f32 / (to - from) as f32);
+
+        for j in height - MARGIN - HEIGHT..height - MARGIN {
+            buf[(j * width + i) as usize] = color;
+        }
+    }
+}
@@ -1,51 +1,141 @@
-use std::{collections::HashMap, mem};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    error, fmt,
+    num::NonZeroU32,
+    path::PathBuf,
+    rc::Rc,
+};
 
+use tangent_proj::texture::LoadError;
 use winit::{
     dpi::PhysicalSize,
+    error::{EventLoopError, OsError},
+    event_loop::OwnedDisplayHandle,
     window::{Window, WindowId},
 };
 
-use softbuffer::{Buffer, Context, Surface};
+use softbuffer::{Buffer, Context, SoftBufferError, Surface};
+
+type Sfc = Surface<OwnedDisplayHandle, Rc<Window>>;
 
-type Sfc = Surface<&'static Window, &'static Window>;
+#[derive(Debug)]
+pub enum GraphicsError {
+    /// No softbuffer context could be made for the display.
+    Context(SoftBufferError),
+    /// A window couldn't be opened.
+    Window(OsError),
+    /// No surface could be made for a window.
+    Surface(SoftBufferError),
+    /// The surface couldn't take the size of its window.
+    Resize(SoftBufferError),
+    /// The buffer of the surface couldn't be had.
+    Buffer(SoftBufferError),
+    /// The frame couldn't be shown.
+    Present(SoftBufferError),
+    /// The texture at this path couldn't be loaded.
+    Texture(PathBuf, LoadError),
+    EventLoop(EventLoopError),
+}
+impl GraphicsError {
+    /// Whether the surface may work again once made anew, as when it was
+    /// lost to a change of display.
+    pub fn is_surface_lost(&self) -> bool {
+        matches!(self, Self::Resize(_) | Self::Buffer(_) | Self::Present(_))
+    }
+}
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Context(e) => write!(f, "can't connect to the display: {e}"),
+            Self::Window(e) => write!(f, "can't open a window: {e}"),
+            Self::Surface(e) => {
+                write!(f, "can't make a surface for the window: {e}")
+            }
+            Self::Resize(e) => write!(f, "can't resize the surface: {e}"),
+            Self::Buffer(e) => write!(f, "can't get the surface buffer: {e}"),
+            Self::Present(e) => write!(f, "can't present the frame: {e}"),
+            Self::Texture(path, e) => {
+                write!(f, "can't load {}: {e}", path.display())
+            }
+            Self::EventLoop(e) => write!(f, "event loop failed: {e}"),
+        }
+    }
+}
+impl error::Error for GraphicsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Context(e)
+            | Self::Surface(e)
+            | Self::Resize(e)
+            | Self::Buffer(e)
+            | Self::Present(e) => Some(e),
+            Self::Window(e) => Some(e),
+            Self::Texture(_, e) => Some(e),
+            Self::EventLoop(e) => Some(e),
+        }
+    }
+}
+impl From<EventLoopError> for GraphicsError {
+    fn from(e: EventLoopError) -> Self {
+        Self::EventLoop(e)
+    }
+}
 
+/// The softbuffer surfaces of the open windows.
+///
+/// Each surface shares ownership of its window, so a window lives until its
+/// surface is removed, however the rest of the app lets go of it.
 pub struct GraphicsCtx {
-    soft_ctx: Context<&'static Window>,
+    soft_ctx: Context<OwnedDisplayHandle>,
     surfaces: HashMap<WindowId, Sfc>,
 }
 impl GraphicsCtx {
-    pub fn new(window: &'static Window) -> Self {
-        Self {
-            soft_ctx: Context::new(window)
-                .expect("failed to create softbuffer context"),
+    pub fn new(display: OwnedDisplayHandle) -> Result<Self, GraphicsError> {
+        Ok(Self {
+            soft_ctx: Context::new(display).map_err(GraphicsError::Context)?,
             surfaces: HashMap::new(),
-        }
+        })
     }
-    fn create_surface(&mut self, window: &Window) -> &mut Sfc {
-        self.surfaces.entry(window.id()).or_insert_with(|| {
-            Surface::new(&self.soft_ctx, unsafe {
-                mem::transmute::<&'_ Window, &'static Window>(window)
-            })
-            .expect("Failed to create a softbuffer surface")
+    fn create_surface(
+        &mut self,
+        window: &Rc<Window>,
+    ) -> Result<&mut Sfc, GraphicsError> {
+        Ok(match self.surfaces.entry(window.id()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                Surface::new(&self.soft_ctx, Rc::clone(window))
+                    .map_err(GraphicsError::Surface)?,
+            ),
         })
     }
 
+    /// Drops the surface of a window, which is made again on the next draw
+    /// if the window is still there.
+    pub fn remove_surface(&mut self, window_id: WindowId) {
+        self.surfaces.remove(&window_id);
+    }
+
+    /// Lets `f` fill the buffer of the window and shows it. Windows without
+    /// area, as while minimized, are skipped.
     pub fn draw(
         &mut self,
-        window: &Window,
-        f: impl FnOnce(&mut Buffer<'_, &Window, &Window>),
-    ) -> Result<(), ()> {
-        let sfc = self.create_surface(window);
+        window: &Rc<Window>,
+        f: impl FnOnce(&mut Buffer<'_, OwnedDisplayHandle, Rc<Window>>),
+    ) -> Result<(), GraphicsError> {
         let PhysicalSize { width, height } = window.inner_size();
-        sfc.resize(width.try_into().unwrap(), height.try_into().unwrap())
-            .expect("can't resize surface");
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(width), NonZeroU32::new(height))
+        else {
+            return Ok(());
+        };
 
-        let mut buf = sfc.buffer_mut().unwrap();
+        let sfc = self.create_surface(window)?;
+        sfc.resize(width, height).map_err(GraphicsError::Resize)?;
 
-        f(&mut buf);
+        let mut buf = sfc.buffer_mut().map_err(GraphicsError::Buffer)?;
 
-        buf.present().expect("can't present buffer");
+        f(&mut buf);
 
-        Ok(())
+        buf.present().map_err(GraphicsError::Present)
     }
 }
@@ -0,0 +1,189 @@
+//! Resampling images of the globe between projections.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    mipmap::Mipmaps,
+    parallel::Pool,
+    projection::{Projection, ProjectionKind},
+    sampler::{AddressMode, Filter, Sampler},
+    sphere::{self, Matrix, Rotation, Vec3},
+};
+
+/// How an image in an azimuthal projection was made, with the meaning the
+/// fields have in [`View`](crate::raster::View).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Azimuthal {
+    pub projection: ProjectionKind,
+    pub rot: Rotation,
+    pub scale: f32,
+    pub cam_offset: [f32; 2],
+}
+
+/// How the pixels of an image lie on the globe.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Georef {
+    /// The whole globe in plate carrée, laid out like a
+    /// [`Texture`](crate::texture::Texture).
+    Equirectangular,
+    /// A view in an azimuthal projection, laid out as the renderer draws it.
+    Azimuthal(Azimuthal),
+}
+
+/// A [`Georef`] for an image of some size, ready to map many points.
+enum Mapping {
+    Equirectangular {
+        size: [f32; 2],
+    },
+    Azimuthal {
+        projection: Box<dyn Projection + Send + Sync>,
+        to_geo: Matrix,
+        to_local: Matrix,
+        /// Pixel at which the plane origin lies.
+        origin: [f32; 2],
+        size: [f32; 2],
+    },
+}
+impl Mapping {
+    fn new(georef: &Georef, [width, height]: [u32; 2]) -> Self {
+        let size = [width as f32, height as f32];
+
+        match georef {
+            Georef::Equirectangular => Self::Equirectangular { size },
+            Georef::Azimuthal(view) => Self::Azimuthal {
+                projection: view.projection.with_scale(view.scale),
+                to_geo: view.rot.matrix(),
+                to_local: view.rot.inverse().matrix(),
+                origin: [
+                    size[0] / 2.0 + view.cam_offset[0],
+                    size[1] / 2.0 + view.cam_offset[1],
+                ],
+                size,
+            },
+        }
+    }
+
+    /// The geographic point at pixel `(i, j)`, with pixel centres at
+    /// integers, or `None` if it's off the projection.
+    fn to_sphere(&self, i: f32, j: f32) -> Option<Vec3> {
+        match self {
+            Self::Equirectangular { size } => Some(sphere::to_unit(
+                FRAC_PI_2 - (j + 0.5) / size[1] * PI,
+                (i + 0.5) / size[0] * 2.0 * PI - PI,
+            )),
+            Self::Azimuthal {
+                projection,
+                to_geo,
+                origin,
+                ..
+            } => {
+                let (lat, lon) =
+                    projection.inverse(i - origin[0], origin[1] - j)?;
+
+                Some(to_geo.apply(sphere::to_unit(lat, lon)))
+            }
+        }
+    }
+
+    /// Pixel coordinates of the geographic point `v`, or `None` if the
+    /// image doesn't show it.
+    fn to_pixel(&self, v: Vec3) -> Option<[f32; 2]> {
+        match self {
+            Self::Equirectangular { size } => {
+                let (lat, lon) = sphere::from_unit(v);
+
+                Some([
+                    (lon + PI) / (2.0 * PI) * size[0] - 0.5,
+                    (FRAC_PI_2 - lat) / PI * size[1] - 0.5,
+                ])
+            }
+            Self::Azimuthal {
+                projection,
+                to_local,
+                origin,
+                size,
+                ..
+            } => {
+                let (lat, lon) = sphere::from_unit(to_local.apply(v));
+                let (x, y) = projection.forward(lat, lon)?;
+                let [i, j] = [origin[0] + x, origin[1] - y];
+
+                // The image covers up to the outer edges of its border
+                // pixels.
+                ((-0.5..size[0] - 0.5).contains(&i)
+                    && (-0.5..size[1] - 0.5).contains(&j))
+                .then_some([i, j])
+            }
+        }
+    }
+
+    /// Distance in pixels between two pixel coordinates, the short way
+    /// round if the image wraps.
+    fn distance(&self, a: [f32; 2], b: [f32; 2]) -> f32 {
+        let (dx, dy) = ((a[0] - b[0]).abs(), a[1] - b[1]);
+
+        match self {
+            Self::Equirectangular { size } => dx.min(size[0] - dx).hypot(dy),
+            Self::Azimuthal { .. } => dx.hypot(dy),
+        }
+    }
+}
+
+/// Resamples `image`, laid out as `source` describes, into a
+/// `width`×`height` image laid out as `target` describes.
+///
+/// The pixels are `ARGB`, with the top byte the opacity: points `image`
+/// doesn't show and pixels off the target projection are transparent black.
+/// Where the image shrinks, it's read from its coarser mip levels.
+pub fn reproject(
+    image: &Mipmaps,
+    source: &Georef,
+    target: &Georef,
+    [width, height]: [u32; 2],
+    filter: Filter,
+    pool: &Pool,
+) -> Vec<u32> {
+    const OPAQUE: u32 = 0xff00_0000;
+
+    let base = image.base();
+    let from = Mapping::new(source, [base.width(), base.height()]);
+    let to = Mapping::new(target, [width, height]);
+    let sampler = match source {
+        Georef::Equirectangular => Sampler::new(image, filter),
+        Georef::Azimuthal(_) => Sampler::new(image, filter)
+            .with_address_modes(AddressMode::Clamp, AddressMode::Clamp),
+    };
+    let source_pixel =
+        |i: f32, j: f32| to.to_sphere(i, j).and_then(|v| from.to_pixel(v));
+
+    let mut pixels = vec![0; width as usize * height as usize];
+    pool.for_each_band(&mut pixels, width as usize, |first, band| {
+        for (r, row) in band.chunks_mut(width as usize).enumerate() {
+            let j = first + r;
+            // Neighbours are taken inside the target, as past its edge
+            // an equirectangular one continues elsewhere on the globe.
+            let dj = if j + 1 < height as usize { 1.0 } else { -1.0 };
+            let j = j as f32;
+
+            for (i, px) in row.iter_mut().enumerate() {
+                let di = if i + 1 < width as usize { 1.0 } else { -1.0 };
+                let i = i as f32;
+                let Some(p) = source_pixel(i, j) else {
+                    continue;
+                };
+                // Source pixels spanned by this one, measured to where
+                // its neighbours land.
+                let footprint =
+                    [source_pixel(i + di, j), source_pixel(i, j + dj)]
+                        .into_iter()
+                        .flatten()
+                        .map(|q| from.distance(p, q))
+                        .fold(0.0, f32::max);
+
+                *px = OPAQUE | sampler.sample_texel(p[0], p[1], footprint);
+            }
+        }
+    });
+
+    pixels
+}
@@ -0,0 +1,265 @@
+//! Filtered lookup of texture colours at geographic coordinates.
+
+use std::{
+    f32::consts::{FRAC_PI_2, PI},
+    fmt,
+};
+
+use crate::{mipmap::Mipmaps, texture::Texture};
+
+/// How texels around a sample point are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+    /// Catmull-Rom interpolation over 4×4 texels.
+    Bicubic,
+}
+impl Filter {
+    pub const ALL: [Self; 3] = [Self::Nearest, Self::Bilinear, Self::Bicubic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nearest => "nearest",
+            Self::Bilinear => "bilinear",
+            Self::Bicubic => "bicubic",
+        }
+    }
+
+    /// The next filter in [`Filter::ALL`], wrapping around.
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&f| f == self).unwrap();
+
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How texel coordinates past an edge of the texture are brought back in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressMode {
+    /// Repeat the texture, as longitudes do around the antimeridian.
+    Wrap,
+    /// Repeat the texels along the edge.
+    Clamp,
+    /// Reflect back at the edge.
+    ///
+    /// When the other axis wraps, a reflection also moves half way round
+    /// along it. That is how the rows past a pole of a globe continue, on
+    /// the opposite meridian.
+    Mirror,
+}
+impl AddressMode {
+    /// Brings a continuous coordinate on an axis of `len` texels into a
+    /// bounded range, so the taps around it can't overflow.
+    fn reduce(self, t: f32, len: u32) -> f32 {
+        match self {
+            Self::Wrap => t.rem_euclid(len as f32),
+            Self::Clamp => t.clamp(-1.0, len as f32),
+            Self::Mirror => t.rem_euclid(2.0 * len as f32),
+        }
+    }
+
+    /// Maps an integer coordinate to a texel on an axis of `len` texels, and
+    /// tells whether it was reflected.
+    fn address(self, i: i64, len: u32) -> (u32, bool) {
+        let len = len as i64;
+
+        match self {
+            Self::Wrap => (i.rem_euclid(len) as u32, false),
+            Self::Clamp => (i.clamp(0, len - 1) as u32, false),
+            Self::Mirror => match i.rem_euclid(2 * len) {
+                i if i < len => (i as u32, false),
+                i => ((2 * len - 1 - i) as u32, true),
+            },
+        }
+    }
+}
+
+/// Reads filtered colours from a texture.
+///
+/// Sampling never panics, whatever the coordinates: non-finite ones give an
+/// unspecified colour.
+#[derive(Debug, Clone, Copy)]
+pub struct Sampler<'a> {
+    mipmaps: &'a Mipmaps,
+    filter: Filter,
+    /// Modes along the columns and rows of the texture.
+    address: [AddressMode; 2],
+}
+impl<'a> Sampler<'a> {
+    /// A sampler for a globe: longitudes wrap and rows mirror across the
+    /// poles.
+    pub fn new(mipmaps: &'a Mipmaps, filter: Filter) -> Self {
+        Self {
+            mipmaps,
+            filter,
+            address: [AddressMode::Wrap, AddressMode::Mirror],
+        }
+    }
+
+    /// Uses `u` past the east and west edges and `v` past the north and
+    /// south ones.
+    pub fn with_address_modes(self, u: AddressMode, v: AddressMode) -> Self {
+        Self {
+            address: [u, v],
+            ..self
+        }
+    }
+
+    /// The `0RGB` colour at geographic `(lat, lon)`, for a pixel spanning an
+    /// arc of `footprint` radians on the sphere.
+    ///
+    /// The mip level is chosen so that a texel covers about the footprint,
+    /// and neighbouring levels are blended unless the filter is
+    /// [`Filter::Nearest`].
+    pub fn sample(&self, lat: f32, lon: f32, footprint: f32) -> u32 {
+        let base = self.mipmaps.base();
+        // Parallels shrink towards the poles while keeping their texels, so
+        // the footprint spans more texels along them than along meridians.
+        let texels_per_radian = (base.height() as f32 / PI)
+            .max(base.width() as f32 / (2.0 * PI * lat.cos().max(1e-3)));
+
+        // Position across the texture, from `0` to `1` along each side.
+        let (s, t) = ((lon + PI) / (2.0 * PI), (FRAC_PI_2 - lat) / PI);
+
+        self.sample_lod(s, t, footprint * texels_per_radian)
+    }
+
+    /// The colour at texel coordinates `(x, y)` of the full resolution
+    /// texture, with texel centres at integers, for a pixel spanning
+    /// `footprint` texels. This treats the texture as a plain image rather
+    /// than a globe.
+    pub fn sample_texel(&self, x: f32, y: f32, footprint: f32) -> u32 {
+        let base = self.mipmaps.base();
+
+        self.sample_lod(
+            (x + 0.5) / base.width() as f32,
+            (y + 0.5) / base.height() as f32,
+            footprint,
+        )
+    }
+
+    /// The colour at `(s, t)`, running from `0` to `1` across the texture,
+    /// from the mip levels where `footprint` full resolution texels shrink
+    /// to about one.
+    fn sample_lod(&self, s: f32, t: f32, footprint: f32) -> u32 {
+        let lod = footprint.log2().min(self.mipmaps.level_count() as f32);
+
+        if lod.is_nan() || lod <= 0.0 {
+            return self.sample_level(self.mipmaps.base(), s, t);
+        }
+        if self.filter == Filter::Nearest {
+            let level = self.mipmaps.level(lod.round() as usize);
+
+            return self.sample_level(level, s, t);
+        }
+
+        let level = lod.floor();
+        let frac = lod - level;
+        let level = level as usize;
+
+        blend(&[
+            (
+                self.sample_level(self.mipmaps.level(level), s, t),
+                1.0 - frac,
+            ),
+            (self.sample_level(self.mipmaps.level(level + 1), s, t), frac),
+        ])
+    }
+
+    /// The colour at `(s, t)`, running from `0` to `1` across the texture.
+    fn sample_level(&self, texture: &Texture, s: f32, t: f32) -> u32 {
+        let (w, h) = (texture.width(), texture.height());
+        let [mode_u, mode_v] = self.address;
+        // Continuous texel coordinates, with texel centres at integers.
+        let u = mode_u.reduce(s * w as f32 - 0.5, w);
+        let v = mode_v.reduce(t * h as f32 - 0.5, h);
+
+        match self.filter {
+            Filter::Nearest => {
+                let (x, y) = ((u + 0.5).floor(), (v + 0.5).floor());
+
+                self.texel(texture, x as i64, y as i64)
+            }
+            Filter::Bilinear => {
+                let (x, y) = (u.floor(), v.floor());
+                let (fx, fy) = (u - x, v - y);
+                let (x, y) = (x as i64, y as i64);
+
+                blend(&[
+                    (self.texel(texture, x, y), (1.0 - fx) * (1.0 - fy)),
+                    (self.texel(texture, x + 1, y), fx * (1.0 - fy)),
+                    (self.texel(texture, x, y + 1), (1.0 - fx) * fy),
+                    (self.texel(texture, x + 1, y + 1), fx * fy),
+                ])
+            }
+            Filter::Bicubic => {
+                let (x, y) = (u.floor(), v.floor());
+                let (wx, wy) = (catmull_rom(u - x), catmull_rom(v - y));
+                let (x, y) = (x as i64, y as i64);
+
+                let mut taps = [(0, 0.0); 16];
+                for (j, wy) in wy.into_iter().enumerate() {
+                    for (i, wx) in wx.into_iter().enumerate() {
+                        taps[4 * j + i] = (
+                            self.texel(
+                                texture,
+                                x + i as i64 - 1,
+                                y + j as i64 - 1,
+                            ),
+                            wx * wy,
+                        );
+                    }
+                }
+
+                blend(&taps)
+            }
+        }
+    }
+
+    /// The texel at integer coordinates, which may lie outside the texture.
+    fn texel(&self, texture: &Texture, x: i64, y: i64) -> u32 {
+        let (w, h) = (texture.width(), texture.height());
+        let [mode_u, mode_v] = self.address;
+
+        let (y, reflected) = mode_v.address(y, h);
+        let x = match (mode_u, reflected) {
+            (AddressMode::Wrap, true) => x + w as i64 / 2,
+            _ => x,
+        };
+        let (x, _) = mode_u.address(x, w);
+
+        texture.texel(x, y)
+    }
+}
+
+/// Weighted sum of `0RGB` colours.
+fn blend(taps: &[(u32, f32)]) -> u32 {
+    let mut sum = [0.0; 3];
+    for &(color, weight) in taps {
+        for (c, s) in sum.iter_mut().enumerate() {
+            *s += ((color >> (16 - 8 * c)) & 0xff) as f32 * weight;
+        }
+    }
+
+    sum.iter()
+        .fold(0, |px, s| (px << 8) | s.round().clamp(0.0, 255.0) as u32)
+}
+
+/// Catmull-Rom weights of the four texels around fractional offset `t`.
+fn catmull_rom(t: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+
+    [
+        (-t3 + 2.0 * t2 - t) / 2.0,
+        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+        (t3 - t2) / 2.0,
+    ]
+}
@@ -0,0 +1,277 @@
+//! Points and rotations on the unit sphere.
+//!
+//! Unit vectors use the geographic frame: `+z` is the north pole and `+x`
+//! lies on the meridian of longitude `0`. The projections work in a local
+//! frame whose north pole is the projection centre; a [`Rotation`] takes
+//! vectors from that local frame to the geographic one.
+
+use std::{f32::consts::FRAC_PI_2, ops::Mul};
+
+pub type Vec3 = [f32; 3];
+
+/// Returns the unit vector at `(lat, lon)`.
+pub fn to_unit(lat: f32, lon: f32) -> Vec3 {
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_lon, cos_lon) = lon.sin_cos();
+
+    [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat]
+}
+
+/// Returns `(lat, lon)` of a unit vector, with `lon` in `[-π, π]`.
+pub fn from_unit([x, y, z]: Vec3) -> (f32, f32) {
+    (z.clamp(-1.0, 1.0).asin(), y.atan2(x))
+}
+
+/// A rotation of the sphere, stored as a unit quaternion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rotation {
+    w: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+}
+impl Rotation {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Rotation by `angle` around `axis`, counter-clockwise when looking
+    /// from the tip of the axis.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let len =
+            (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let s = sin / len;
+
+        Self {
+            w: cos,
+            x: axis[0] * s,
+            y: axis[1] * s,
+            z: axis[2] * s,
+        }
+    }
+
+    /// The rotation that puts the projection centre at `(lat, lon)`, with
+    /// north up once the view is turned back by `roll` around it.
+    pub fn from_center(lat: f32, lon: f32, roll: f32) -> Self {
+        // The quarter turn takes the local meridian that points up to the
+        // one towards the geographic north.
+        Self::from_axis_angle([0.0, 0.0, 1.0], lon)
+            * Self::from_axis_angle([0.0, 1.0, 0.0], FRAC_PI_2 - lat)
+            * Self::from_axis_angle([0.0, 0.0, 1.0], roll + FRAC_PI_2)
+    }
+
+    /// Geographic `(lat, lon)` of the projection centre.
+    pub fn center(&self) -> (f32, f32) {
+        from_unit(self.rotate([0.0, 0.0, 1.0]))
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        self.matrix().apply(v)
+    }
+
+    /// The rotation as a matrix, which is cheaper to apply repeatedly.
+    pub fn matrix(&self) -> Matrix {
+        let Self { w, x, y, z } = *self;
+
+        Matrix([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ])
+    }
+
+    /// The rotation a fraction `t` of the way from `self` to `other`, turning
+    /// at a constant rate along the shortest way.
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        let dot = self.w * other.w
+            + self.x * other.x
+            + self.y * other.y
+            + self.z * other.z;
+        // `q` and `-q` are the same rotation; the nearer one is the shorter
+        // way round.
+        let (other, dot) = if dot < 0.0 {
+            (
+                Self {
+                    w: -other.w,
+                    x: -other.x,
+                    y: -other.y,
+                    z: -other.z,
+                },
+                -dot,
+            )
+        } else {
+            (other, dot)
+        };
+
+        // Nearly equal rotations are interpolated linearly, where the angle
+        // between them can't be told precisely.
+        let (a, b) = if dot > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = dot.acos();
+            let sin = angle.sin();
+
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+
+        Self {
+            w: a * self.w + b * other.w,
+            x: a * self.x + b * other.x,
+            y: a * self.y + b * other.y,
+            z: a * self.z + b * other.z,
+        }
+        .normalized()
+    }
+
+    fn normalized(self) -> Self {
+        let len = (self.w * self.w
+            + self.x * self.x
+            + self.y * self.y
+            + self.z * self.z)
+            .sqrt();
+
+        Self {
+            w: self.w / len,
+            x: self.x / len,
+            y: self.y / len,
+            z: self.z / len,
+        }
+    }
+}
+impl Default for Rotation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+/// `a * b` applies `b` first, then `a`.
+impl Mul for Rotation {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (self, rhs);
+
+        Self {
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        }
+        .normalized()
+    }
+}
+
+/// A row-major 3×3 rotation matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix(pub [[f32; 3]; 3]);
+impl Matrix {
+    pub fn apply(&self, [x, y, z]: Vec3) -> Vec3 {
+        let m = &self.0;
+
+        [
+            m[0][0] * x + m[0][1] * y + m[0][2] * z,
+            m[1][0] * x + m[1][1] * y + m[1][2] * z,
+            m[2][0] * x + m[2][1] * y + m[2][2] * z,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const X: Vec3 = [1.0, 0.0, 0.0];
+    const Z: Vec3 = [0.0, 0.0, 1.0];
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        let d = (0..3).map(|k| (a[k] - b[k]).abs()).fold(0.0, f32::max);
+        assert!(d < 1e-5, "{a:?} != {b:?}");
+    }
+
+    /// Asserts that two rotations move the axes the same way, whichever of
+    /// `q` and `-q` they're stored as.
+    fn assert_same(a: Rotation, b: Rotation) {
+        for v in [X, [0.0, 1.0, 0.0], Z] {
+            assert_near(a.rotate(v), b.rotate(v));
+        }
+    }
+
+    #[test]
+    fn center_is_where_it_was_put() {
+        for (lat, lon, roll) in
+            [(0.0, 0.0, 0.0), (0.8, 0.2, 0.5), (-1.2, -2.1, -1.6)]
+        {
+            let (lat2, lon2) = Rotation::from_center(lat, lon, roll).center();
+
+            assert_near([lat2, lon2, 0.0], [lat, lon, 0.0]);
+        }
+    }
+
+    #[test]
+    fn product_applies_the_right_factor_first() {
+        let a = Rotation::from_axis_angle(Z, FRAC_PI_2);
+        let b = Rotation::from_axis_angle(X, FRAC_PI_2);
+        let v = [0.0, 1.0, 0.0];
+
+        assert_near((a * b).rotate(v), a.rotate(b.rotate(v)));
+        // `b` takes `v` to the pole, which `a` keeps.
+        assert_near((a * b).rotate(v), Z);
+        assert_near((b * a).rotate(v), [-1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn slerp_ends_at_its_endpoints() {
+        let a = Rotation::from_center(0.3, -1.0, 0.2);
+        let b = Rotation::from_center(-0.8, 2.5, 1.0);
+
+        assert_same(a.slerp(b, 0.0), a);
+        assert_same(a.slerp(b, 1.0), b);
+    }
+
+    #[test]
+    fn slerp_turns_the_shorter_way() {
+        // A turn of -190° stored as such is the same rotation as one of
+        // 170°, and halfway there is a turn of 85°, not -95°.
+        let b = Rotation::from_axis_angle(Z, (-190.0f32).to_radians());
+        let half = Rotation::IDENTITY.slerp(b, 0.5);
+        let (sin, cos) = 85.0f32.to_radians().sin_cos();
+
+        assert_near(half.rotate(X), [cos, sin, 0.0]);
+    }
+
+    #[test]
+    fn turning_past_a_pole_continues_on_the_far_side() {
+        // Tilting the view northwards by 20° from 80° N takes the centre
+        // over the pole to 80° N on the opposite meridian.
+        let rot = Rotation::from_center(80f32.to_radians(), 0.0, 0.0)
+            * Rotation::from_axis_angle(X, (-20.0f32).to_radians());
+        let (lat, lon) = rot.center();
+
+        assert!((lat.to_degrees() - 80.0).abs() < 1e-3, "lat {lat}");
+        assert!((lon.abs().to_degrees() - 180.0).abs() < 1e-3, "lon {lon}");
+    }
+}
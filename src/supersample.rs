@@ -0,0 +1,146 @@
+//! Supersampling: shading several points per pixel and averaging them.
+
+use std::{fmt, sync::OnceLock};
+
+/// Where the samples fall inside a pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Pattern {
+    /// At the centres of a regular grid.
+    #[default]
+    Grid,
+    /// Randomly within each cell of the grid. The offsets depend only on the
+    /// pixel, so still frames don't flicker.
+    Jittered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Supersampling {
+    /// Samples along each side of a pixel.
+    pub grid: u32,
+    pub pattern: Pattern,
+}
+impl Supersampling {
+    /// One sample in the centre of each pixel.
+    pub const OFF: Self = Self {
+        grid: 1,
+        pattern: Pattern::Grid,
+    };
+
+    /// Largest number of samples along a side.
+    pub const MAX_GRID: u32 = 4;
+
+    pub fn samples(&self) -> u32 {
+        self.grid * self.grid
+    }
+
+    /// The next sample count in 1, 4, 9, 16, wrapping around.
+    pub fn next(self) -> Self {
+        Self {
+            grid: self.grid % Self::MAX_GRID + 1,
+            ..self
+        }
+    }
+
+    /// Offsets of the samples of pixel `(i, j)` from its centre, in pixels.
+    pub fn offsets(&self, i: u32, j: u32) -> impl Iterator<Item = [f32; 2]> {
+        let Self { grid, pattern } = *self;
+
+        (0..grid * grid).map(move |k| {
+            let cell = [(k % grid) as f32, (k / grid) as f32];
+            let within = match pattern {
+                Pattern::Grid => [0.5, 0.5],
+                Pattern::Jittered => {
+                    let bits = hash(i, j, k);
+                    [
+                        (bits & 0xffff) as f32 / 65536.0,
+                        (bits >> 16) as f32 / 65536.0,
+                    ]
+                }
+            };
+
+            [
+                (cell[0] + within[0]) / grid as f32 - 0.5,
+                (cell[1] + within[1]) / grid as f32 - 0.5,
+            ]
+        })
+    }
+}
+impl Default for Supersampling {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+impl fmt::Display for Supersampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pattern {
+            Pattern::Grid => write!(f, "{}x grid SSAA", self.samples()),
+            Pattern::Jittered => write!(f, "{}x jittered SSAA", self.samples()),
+        }
+    }
+}
+
+/// Mixes the bits of a sample's coordinates.
+fn hash(i: u32, j: u32, k: u32) -> u32 {
+    let mut h = i
+        .wrapping_mul(0x9e37_79b1)
+        .wrapping_add(j.wrapping_mul(0x85eb_ca77))
+        .wrapping_add(k.wrapping_mul(0xc2b2_ae3d));
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^ (h >> 15)
+}
+
+/// Averages `0RGB` colours in linear light rather than in sRGB.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearAverage {
+    sum: [f32; 3],
+    count: u32,
+}
+impl LinearAverage {
+    pub fn add(&mut self, color: u32) {
+        let lut = to_linear_lut();
+
+        for (c, sum) in self.sum.iter_mut().enumerate() {
+            *sum += lut[((color >> (16 - 8 * c)) & 0xff) as usize];
+        }
+        self.count += 1;
+    }
+
+    /// The average colour, black if nothing was added.
+    pub fn color(&self) -> u32 {
+        if self.count == 0 {
+            return 0;
+        }
+
+        self.sum.iter().fold(0, |px, &sum| {
+            (px << 8) | to_srgb(sum / self.count as f32) as u32
+        })
+    }
+}
+
+fn to_linear_lut() -> &'static [f32; 256] {
+    static LUT: OnceLock<[f32; 256]> = OnceLock::new();
+
+    LUT.get_or_init(|| {
+        std::array::from_fn(|v| {
+            let v = v as f32 / 255.0;
+            if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    })
+}
+
+fn to_srgb(linear: f32) -> u8 {
+    let v = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+
+    (v * 255.0).round().clamp(0.0, 255.0) as u8
+}
@@ -0,0 +1,148 @@
+//! Equirectangular images of the globe.
+
+use std::{error, fmt, fs, io, path::Path};
+
+use crate::{bmp::BmpError, format};
+
+/// Largest image accepted by the decoders, in pixels.
+pub const MAX_PIXELS: usize = 1 << 28;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// The data isn't in any supported image format.
+    UnknownFormat,
+    /// The image has more than [`MAX_PIXELS`] pixels.
+    TooLarge {
+        width: u32,
+        height: u32,
+    },
+    Bmp(BmpError),
+    Png(png::DecodingError),
+    Jpeg(jpeg_decoder::Error),
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::UnknownFormat => write!(f, "unknown image format"),
+            Self::TooLarge { width, height } => write!(
+                f,
+                "{width}×{height} image has more than {MAX_PIXELS} pixels"
+            ),
+            Self::Bmp(e) => e.fmt(f),
+            Self::Png(e) => e.fmt(f),
+            Self::Jpeg(e) => e.fmt(f),
+        }
+    }
+}
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::UnknownFormat | Self::TooLarge { .. } => None,
+            Self::Bmp(e) => Some(e),
+            Self::Png(e) => Some(e),
+            Self::Jpeg(e) => Some(e),
+        }
+    }
+}
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<BmpError> for LoadError {
+    fn from(e: BmpError) -> Self {
+        Self::Bmp(e)
+    }
+}
+impl From<png::DecodingError> for LoadError {
+    fn from(e: png::DecodingError) -> Self {
+        Self::Png(e)
+    }
+}
+impl From<jpeg_decoder::Error> for LoadError {
+    fn from(e: jpeg_decoder::Error) -> Self {
+        Self::Jpeg(e)
+    }
+}
+
+/// An equirectangular image, stored row by row from the north pole down,
+/// each row running east from longitude `-π`. Pixels are `0RGB`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+}
+impl Texture {
+    /// # Panics
+    ///
+    /// If either dimension is zero or `pixels` doesn't hold exactly
+    /// `width * height` values.
+    pub fn new(width: u32, height: u32, pixels: Vec<u32>) -> Self {
+        assert!(width > 0 && height > 0, "empty texture");
+        assert_eq!(pixels.len(), width as usize * height as usize);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Reads an image file in any supported [`format`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        format::decode(&fs::read(path)?)
+    }
+
+    /// A generated texture with a graticule every 15°, used when no image is
+    /// given. The equator and the prime meridian are drawn in red.
+    pub fn graticule(width: u32, height: u32) -> Self {
+        const CELLS: [u32; 2] = [0x1d3557, 0x274c77];
+        const LINE: u32 = 0xd0d0d0;
+        const AXIS: u32 = 0xe63946;
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                // Position in units of 15°, from the south-west corner.
+                let u = x as f32 / width as f32 * 24.0;
+                let v = (height - 1 - y) as f32 / height as f32 * 12.0;
+                let step = [24.0 / width as f32, 12.0 / height as f32];
+
+                let on_line = |t: f32, step: f32| t.fract() < step;
+                let on_axis =
+                    |t: f32, at: f32, step: f32| (at..at + step).contains(&t);
+
+                if on_axis(u, 12.0, step[0]) || on_axis(v, 6.0, step[1]) {
+                    AXIS
+                } else if on_line(u, step[0]) || on_line(v, step[1]) {
+                    LINE
+                } else {
+                    CELLS[(u as usize + v as usize) % 2]
+                }
+            })
+            .collect();
+
+        Self::new(width, height, pixels)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// The pixel in column `x` of row `y`.
+    pub fn texel(&self, x: u32, y: u32) -> u32 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
@@ -0,0 +1,89 @@
+//! Tissot's indicatrices: images of small circles on the sphere, which show
+//! how a projection distorts shapes and areas around each point.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    projection::Projection,
+    sphere::{self, Rotation},
+};
+
+/// Spacing of the geographic lattice the indicatrices are placed on.
+pub const SPACING: f32 = 15.0 * PI / 180.0;
+
+/// Angular radius of the projected circles.
+pub const RADIUS: f32 = 2.5 * PI / 180.0;
+
+/// A projected small circle, in plane coordinates.
+///
+/// Points on its boundary are `center + axes[0] cos t + axes[1] sin t`,
+/// where `axes` are the images of the circle's radii towards north and east.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Indicatrix {
+    pub center: [f32; 2],
+    pub axes: [[f32; 2]; 2],
+}
+impl Indicatrix {
+    /// The indicatrix of radius [`RADIUS`] at `(lat, lon)` in the polar
+    /// aspect of `projection`.
+    pub fn at(projection: &dyn Projection, lat: f32, lon: f32) -> Option<Self> {
+        let (x, y) = projection.forward(lat, lon)?;
+
+        let [[n_x, e_x], [n_y, e_y]] = projection.arc_jacobian(lat, lon)?;
+
+        let indicatrix = Self {
+            center: [x, y],
+            axes: [[n_x * RADIUS, n_y * RADIUS], [e_x * RADIUS, e_y * RADIUS]],
+        };
+
+        indicatrix.det().is_normal().then_some(indicatrix)
+    }
+
+    /// Half-extents of the bounding box around `center`.
+    pub fn extent(&self) -> [f32; 2] {
+        let [u, v] = self.axes;
+
+        [u[0].hypot(v[0]), u[1].hypot(v[1])]
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let [u, v] = self.axes;
+        let [dx, dy] = [x - self.center[0], y - self.center[1]];
+        let det = self.det();
+
+        let a = (dx * v[1] - v[0] * dy) / det;
+        let b = (u[0] * dy - dx * u[1]) / det;
+
+        a * a + b * b <= 1.0
+    }
+
+    fn det(&self) -> f32 {
+        let [u, v] = self.axes;
+
+        u[0] * v[1] - v[0] * u[1]
+    }
+}
+
+/// Indicatrices on a regular geographic lattice, for a view rotated by
+/// `rot`.
+pub fn lattice(projection: &dyn Projection, rot: &Rotation) -> Vec<Indicatrix> {
+    let to_local = rot.inverse().matrix();
+    let rows = (FRAC_PI_2 / SPACING).round() as i32;
+    let cols = (PI / SPACING).round() as i32;
+
+    let mut out = vec![];
+    for row in -rows + 1..rows {
+        for col in -cols..cols {
+            let geo =
+                sphere::to_unit(row as f32 * SPACING, col as f32 * SPACING);
+            let (lat, lon) = sphere::from_unit(to_local.apply(geo));
+
+            if FRAC_PI_2 - lat > projection.domain() {
+                continue;
+            }
+            out.extend(Indicatrix::at(projection, lat, lon));
+        }
+    }
+
+    out
+}
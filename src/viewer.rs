@@ -0,0 +1,590 @@
+//! The interactive viewer: windows whose views follow the mouse and the
+//! keyboard.
+
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    ops::Range,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use tangent_proj::{
+    distortion::Metric,
+    mipmap::Mipmaps,
+    parallel::Pool,
+    projection::ProjectionKind,
+    raster::{PaneCache, Renderer, View},
+    sphere::Rotation,
+    supersample::Pattern,
+    texture::Texture,
+};
+use winit::{
+    application::ApplicationHandler,
+    dpi::{LogicalPosition, LogicalSize, PhysicalSize, Size},
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::KeyCode,
+    window::{Window, WindowId},
+};
+
+use crate::render::{GraphicsCtx, GraphicsError};
+
+/// How close to the divider, in pixels, a click grabs it.
+const DIVIDER_GRAB: f32 = 4.0;
+
+/// How long input has to stop before the frame is refined.
+const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// How many frames in a row may fail to show, each on a new surface, before
+/// the viewer gives up.
+const MAX_LOST_FRAMES: u32 = 3;
+
+/// A second pane showing the same view through another projection.
+#[derive(Debug, Clone, Copy)]
+struct Split {
+    /// Position of the divider, as a fraction of the window width.
+    ratio: f32,
+    projection: ProjectionKind,
+    dragging: bool,
+}
+
+/// A change of view that linked windows make together.
+#[derive(Debug, Clone, Copy)]
+enum Motion {
+    /// A turn around the axes of the view.
+    Turn(Rotation),
+    /// A shift of the projection centre, in pixels.
+    Pan([f32; 2]),
+    /// A factor the scale grows by.
+    Zoom(f32),
+}
+impl Motion {
+    fn apply(self, view: &mut View) {
+        match self {
+            Self::Turn(rot) => view.rot = view.rot * rot,
+            Self::Pan([dx, dy]) => {
+                view.cam_offset[0] += dx;
+                view.cam_offset[1] += dy;
+            }
+            Self::Zoom(factor) => view.scale *= factor,
+        }
+    }
+}
+
+/// A window and the view it shows.
+struct ViewWindow {
+    window: Rc<Window>,
+
+    view: View,
+    drag: bool,
+    mouse_pos: [f32; 2],
+    projection: ProjectionKind,
+    split: Option<Split>,
+    /// Whether the view moves along with the other linked ones.
+    linked: bool,
+    /// When input is taken to have settled, while frames are rendered at
+    /// reduced resolution to keep up with it.
+    settle: Option<Instant>,
+    /// One for each pane.
+    caches: [PaneCache; 2],
+    /// Frames in a row that failed to show.
+    lost_frames: u32,
+}
+impl ViewWindow {
+    fn new(window: Rc<Window>) -> Self {
+        Self {
+            window,
+
+            view: View::default(),
+            drag: false,
+            mouse_pos: [0.0, 0.0],
+            projection: ProjectionKind::default(),
+            split: None,
+            linked: false,
+            settle: None,
+            caches: Default::default(),
+            lost_frames: 0,
+        }
+    }
+
+    /// Shows in `window` what this window shows.
+    fn duplicate(&self, window: Rc<Window>) -> Self {
+        Self {
+            view: self.view,
+            projection: self.projection,
+            split: self.split.map(|split| Split {
+                dragging: false,
+                ..split
+            }),
+            linked: self.linked,
+            ..Self::new(window)
+        }
+    }
+
+    fn window_title(&self, name: &str) -> String {
+        let title = match &self.split {
+            Some(split) => {
+                format!("{name} ({} | {})", self.projection, split.projection)
+            }
+            None => format!("{name} ({})", self.projection),
+        };
+
+        let title = match self.view.metric {
+            Some(metric) => format!("{title} - {metric}, {}", metric.legend()),
+            None => format!("{title} - {} filtering", self.view.filter),
+        };
+
+        let title = match self.view.ssaa.samples() {
+            1 => title,
+            _ => format!("{title}, {}", self.view.ssaa),
+        };
+
+        if self.linked {
+            format!("{title}, linked")
+        } else {
+            title
+        }
+    }
+
+    fn update_title(&self, name: &str) {
+        self.window.set_title(&self.window_title(name));
+        self.redraw();
+    }
+
+    fn toggle_split(&mut self) {
+        self.split = match self.split {
+            Some(_) => None,
+            None => Some(Split {
+                ratio: 0.5,
+                projection: match self.projection {
+                    ProjectionKind::Tangent => ProjectionKind::Stereographic,
+                    _ => ProjectionKind::Tangent,
+                },
+                dragging: false,
+            }),
+        };
+    }
+
+    /// Horizontal position of the divider in physical pixels, if split.
+    fn divider(&self) -> Option<f32> {
+        let split = self.split.as_ref()?;
+        let width = self.window.inner_size().width;
+
+        Some(split.ratio * width as f32)
+    }
+
+    /// Redraws coarsely until input has stopped for [`SETTLE_TIME`].
+    fn interact(&mut self) {
+        self.settle = Some(Instant::now() + SETTLE_TIME);
+        self.redraw();
+    }
+
+    fn redraw(&self) {
+        self.window.request_redraw();
+    }
+}
+
+pub struct App {
+    title: String,
+    gtx: Option<GraphicsCtx>,
+    windows: HashMap<WindowId, ViewWindow>,
+    /// Threads the frames are rendered with, kept between frames.
+    pool: Pool,
+    /// What ended the event loop, if it didn't end normally.
+    error: Option<GraphicsError>,
+
+    texture: Mipmaps,
+}
+impl App {
+    pub fn new(title: String, texture: Mipmaps, threads: NonZeroUsize) -> Self {
+        Self {
+            title,
+            gtx: None,
+            windows: HashMap::new(),
+            pool: Pool::new(threads),
+            error: None,
+
+            texture,
+        }
+    }
+
+    /// Opens a window showing what the window `from` shows, or the default
+    /// view without one.
+    fn open_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        from: Option<WindowId>,
+    ) -> Result<(), GraphicsError> {
+        let source = from.and_then(|id| self.windows.get(&id));
+        let size: Size = match source {
+            Some(source) => source.window.inner_size().into(),
+            None => LogicalSize::new(640.0, 320.0).into(),
+        };
+        let mut attributes = Window::default_attributes().with_inner_size(size);
+        // Later windows are placed by the system, so they don't cover the
+        // first.
+        if self.windows.is_empty() {
+            attributes =
+                attributes.with_position(LogicalPosition::new(0.0, 0.0));
+        }
+
+        let window = Rc::new(
+            event_loop
+                .create_window(attributes)
+                .map_err(GraphicsError::Window)?,
+        );
+        let view_window = match source {
+            Some(source) => source.duplicate(window),
+            None => ViewWindow::new(window),
+        };
+        view_window.update_title(&self.title);
+        self.windows.insert(view_window.window.id(), view_window);
+
+        Ok(())
+    }
+
+    /// Moves the view of window `id`, and those of the other linked windows
+    /// if it's linked.
+    fn move_view(&mut self, id: WindowId, motion: Motion) {
+        let linked = self.windows.get(&id).is_some_and(|w| w.linked);
+
+        for (&other, view_window) in &mut self.windows {
+            if other != id && !(linked && view_window.linked) {
+                continue;
+            }
+
+            motion.apply(&mut view_window.view);
+            match motion {
+                Motion::Turn(_) => view_window.redraw(),
+                Motion::Pan(_) | Motion::Zoom(_) => view_window.interact(),
+            }
+        }
+    }
+}
+impl ApplicationHandler<()> for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.gtx.is_none() {
+            match GraphicsCtx::new(event_loop.owned_display_handle()) {
+                Ok(gtx) => self.gtx = Some(gtx),
+                Err(e) => {
+                    self.error = Some(e);
+                    event_loop.exit();
+                    return;
+                }
+            }
+        }
+        if self.windows.is_empty() {
+            if let Err(e) = self.open_window(event_loop, None) {
+                self.error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    /// Drops the surfaces, which some platforms invalidate while suspended;
+    /// the next frames make new ones.
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(gtx) = &mut self.gtx {
+            for &id in self.windows.keys() {
+                gtx.remove_surface(id);
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let now = Instant::now();
+        let mut next_settle = None;
+
+        for view_window in self.windows.values_mut() {
+            match view_window.settle {
+                Some(at) if now >= at => {
+                    view_window.settle = None;
+                    view_window.redraw();
+                }
+                Some(at) => {
+                    next_settle =
+                        Some(next_settle.map_or(at, |n: Instant| n.min(at)));
+                }
+                None => {}
+            }
+        }
+
+        event_loop.set_control_flow(match next_settle {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Wait,
+        });
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let title = &self.title;
+        let Some(view_window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+
+        match event {
+            WindowEvent::CloseRequested => {
+                self.windows.remove(&window_id);
+                if let Some(gtx) = &mut self.gtx {
+                    gtx.remove_surface(window_id);
+                }
+                if self.windows.is_empty() {
+                    event_loop.exit();
+                }
+            }
+            WindowEvent::KeyboardInput {
+                device_id: _,
+                event,
+                is_synthetic: _,
+            } if event.state.is_pressed() => {
+                let winit::keyboard::PhysicalKey::Code(key_code) =
+                    event.physical_key
+                else {
+                    return;
+                };
+
+                let digits = [
+                    KeyCode::Digit1,
+                    KeyCode::Digit2,
+                    KeyCode::Digit3,
+                    KeyCode::Digit4,
+                    KeyCode::Digit5,
+                    KeyCode::Digit6,
+                ];
+                match key_code {
+                    KeyCode::KeyN => {
+                        // Failing to open another window leaves the others
+                        // working.
+                        if let Err(e) =
+                            self.open_window(event_loop, Some(window_id))
+                        {
+                            eprintln!("{e}");
+                        }
+                        return;
+                    }
+                    KeyCode::KeyL => {
+                        view_window.linked = !view_window.linked;
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyP => {
+                        view_window.projection = view_window.projection.next();
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyO => {
+                        if let Some(split) = &mut view_window.split {
+                            split.projection = split.projection.next();
+                            view_window.update_title(title);
+                        }
+                        return;
+                    }
+                    KeyCode::KeyS => {
+                        view_window.toggle_split();
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyM => {
+                        let view = &mut view_window.view;
+                        view.metric = match view.metric {
+                            Some(metric) => metric.next(),
+                            None => Some(Metric::ALL[0]),
+                        };
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyF => {
+                        view_window.view.filter =
+                            view_window.view.filter.next();
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyA => {
+                        view_window.view.ssaa = view_window.view.ssaa.next();
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyJ => {
+                        let ssaa = &mut view_window.view.ssaa;
+                        ssaa.pattern = match ssaa.pattern {
+                            Pattern::Grid => Pattern::Jittered,
+                            Pattern::Jittered => Pattern::Grid,
+                        };
+                        view_window.update_title(title);
+                        return;
+                    }
+                    KeyCode::KeyT => {
+                        view_window.view.tissot = !view_window.view.tissot;
+                        view_window.redraw();
+                        return;
+                    }
+                    _ => {}
+                }
+                if let Some(idx) = digits.iter().position(|&k| k == key_code) {
+                    view_window.projection = ProjectionKind::ALL[idx];
+                    view_window.update_title(title);
+                    return;
+                }
+
+                // Turns are taken around the axes of the view, so the globe
+                // moves in the direction of the arrow whatever its
+                // orientation.
+                let amount = 0.1;
+                let (axis, angle) = match key_code {
+                    KeyCode::ArrowLeft => ([0.0, 1.0, 0.0], amount),
+                    KeyCode::ArrowRight => ([0.0, 1.0, 0.0], -amount),
+                    KeyCode::ArrowUp => ([1.0, 0.0, 0.0], amount),
+                    KeyCode::ArrowDown => ([1.0, 0.0, 0.0], -amount),
+                    KeyCode::KeyQ => ([0.0, 0.0, 1.0], amount),
+                    KeyCode::KeyE => ([0.0, 0.0, 1.0], -amount),
+                    _ => {
+                        return;
+                    }
+                };
+                self.move_view(
+                    window_id,
+                    Motion::Turn(Rotation::from_axis_angle(axis, angle)),
+                );
+            }
+            WindowEvent::CursorMoved {
+                device_id: _,
+                position,
+            } => {
+                let [dx, dy] = [
+                    view_window.mouse_pos[0] - position.x as f32,
+                    view_window.mouse_pos[1] - position.y as f32,
+                ];
+                view_window.mouse_pos = [position.x as f32, position.y as f32];
+                if let Some(split) = &mut view_window.split {
+                    if split.dragging {
+                        let width =
+                            view_window.window.inner_size().width.max(1);
+                        split.ratio = (view_window.mouse_pos[0] / width as f32)
+                            .clamp(0.1, 0.9);
+                        view_window.interact();
+                        return;
+                    }
+                }
+                if view_window.drag {
+                    self.move_view(window_id, Motion::Pan([-dx, -dy]));
+                }
+            }
+            WindowEvent::MouseWheel {
+                device_id: _,
+                delta,
+                phase: _,
+            } => {
+                let amount = match delta {
+                    winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                    winit::event::MouseScrollDelta::PixelDelta(pt) => {
+                        pt.y as f32
+                    }
+                };
+                self.move_view(window_id, Motion::Zoom(1.01f32.powf(amount)));
+            }
+            WindowEvent::MouseInput {
+                device_id: _,
+                state,
+                button: winit::event::MouseButton::Left,
+            } => {
+                let pressed = state.is_pressed();
+                let on_divider = view_window.divider().is_some_and(|x| {
+                    (x - view_window.mouse_pos[0]).abs() <= DIVIDER_GRAB
+                });
+
+                if let Some(split) = &mut view_window.split {
+                    split.dragging = pressed && on_divider;
+                }
+                view_window.drag = pressed && !on_divider;
+            }
+            WindowEvent::RedrawRequested => {
+                let Some(gtx) = &mut self.gtx else {
+                    return;
+                };
+                let window = &view_window.window;
+
+                let PhysicalSize { width, height } = window.inner_size();
+                let renderer = Renderer::new(&self.texture, &self.pool)
+                    .with_coarse(view_window.settle.is_some());
+                let view = &view_window.view;
+                let draw_pane =
+                    |buf: &mut [u32],
+                     cols: Range<u32>,
+                     projection: ProjectionKind,
+                     cache: &mut PaneCache| {
+                        renderer.draw_pane(
+                            buf,
+                            [width, height],
+                            cols,
+                            projection,
+                            view,
+                            cache,
+                        );
+                    };
+
+                let projection = view_window.projection;
+                let [left, right] = &mut view_window.caches;
+                let drawn = gtx.draw(window, |buf| match &view_window.split {
+                    Some(split) => {
+                        let divider = ((split.ratio * width as f32) as u32)
+                            .clamp(1, width.max(2) - 1);
+
+                        draw_pane(buf, 0..divider, projection, left);
+                        draw_pane(buf, divider..width, split.projection, right);
+
+                        for j in 0..height {
+                            for i in divider - 1..(divider + 1).min(width) {
+                                buf[(j * width + i) as usize] = 0xffffff;
+                            }
+                        }
+                    }
+                    None => draw_pane(buf, 0..width, projection, left),
+                });
+
+                match drawn {
+                    Ok(()) => view_window.lost_frames = 0,
+                    Err(e)
+                        if e.is_surface_lost()
+                            && view_window.lost_frames < MAX_LOST_FRAMES =>
+                    {
+                        view_window.lost_frames += 1;
+                        gtx.remove_surface(window_id);
+                        view_window.redraw();
+                    }
+                    Err(e) => {
+                        self.error = Some(e);
+                        event_loop.exit();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Opens a window on the texture at `path`, or on the graticule without
+/// one, and runs until all windows are closed.
+pub fn run(
+    path: Option<&Path>,
+    threads: NonZeroUsize,
+) -> Result<(), GraphicsError> {
+    let texture = match path {
+        Some(path) => Texture::load(path)
+            .map_err(|e| GraphicsError::Texture(path.to_owned(), e))?,
+        None => Texture::graticule(1440, 720),
+    };
+
+    let ev_loop = EventLoop::<()>::with_user_event().build()?;
+
+    let mut app =
+        App::new("tangent-proj".to_string(), Mipmaps::new(texture), threads);
+    ev_loop.run_app(&mut app)?;
+
+    app.error.map_or(Ok(()), Err)
+}
@@ -0,0 +1,189 @@
+//! Renders compared against the images in `tests/golden`.
+//!
+//! After a change that is meant to alter what views look like, regenerate
+//! the images with `UPDATE_GOLDENS=1 cargo test --test golden` and check the
+//! new ones by eye before committing them.
+
+use std::{env, path::PathBuf};
+
+use tangent_proj::{
+    export,
+    mipmap::Mipmaps,
+    parallel::{self, Pool},
+    projection::ProjectionKind,
+    raster::{Renderer, View},
+    sphere::Rotation,
+    texture::Texture,
+};
+
+const SIZE: [u32; 2] = [128, 96];
+
+/// Largest difference a pixel may have and still count as the same, on the
+/// scale of [`difference`].
+const THRESHOLD: f32 = 0.1;
+
+/// Share of pixels that may differ, for rounding that varies between
+/// platforms along edges.
+const MAX_DIFFERING: f32 = 0.002;
+
+/// A small graticule whose red and green ramp up to the east and south, so
+/// that views which are shifted or mirrored look different.
+fn texture() -> Mipmaps {
+    let (width, height) = (128, 64);
+    let grid = Texture::graticule(width, height);
+    let pixels = grid
+        .pixels()
+        .iter()
+        .enumerate()
+        .map(|(k, px)| {
+            let (x, y) = (k as u32 % width, k as u32 / width);
+
+            (x * 255 / (width - 1)) << 16
+                | (y * 255 / (height - 1)) << 8
+                | px & 0xff
+        })
+        .collect();
+
+    Mipmaps::new(Texture::new(width, height, pixels))
+}
+
+/// Perceived difference between two `0RGB` colours, from `0` for the same
+/// colour to `1` for black against white.
+///
+/// This is the distance in YIQ space used by `pixelmatch`, which weighs
+/// brightness above hue much as the eye does.
+fn difference(a: u32, b: u32) -> f32 {
+    let yiq = |px: u32| {
+        let [r, g, b] = [16, 8, 0].map(|s| ((px >> s) & 0xff) as f32);
+
+        [
+            0.2989 * r + 0.5866 * g + 0.1145 * b,
+            0.5960 * r - 0.2742 * g - 0.3218 * b,
+            0.2115 * r - 0.5226 * g + 0.3111 * b,
+        ]
+    };
+    let ([y0, i0, q0], [y1, i1, q1]) = (yiq(a), yiq(b));
+    let delta = 0.5053 * (y0 - y1).powi(2)
+        + 0.299 * (i0 - i1).powi(2)
+        + 0.1957 * (q0 - q1).powi(2);
+
+    (delta / 35215.0).sqrt()
+}
+
+/// Renders `view` through `projection` and compares it with the golden
+/// image `name`, or replaces that image if `UPDATE_GOLDENS` is set.
+fn check(name: &str, projection: ProjectionKind, view: View) {
+    let [width, height] = SIZE;
+    let mipmaps = texture();
+    let pixels =
+        Renderer::new(&mipmaps, &Pool::new(parallel::default_threads()))
+            .render(SIZE, projection, &view);
+
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+        .with_extension("png");
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        export::save(&golden, width, height, &pixels).unwrap();
+        return;
+    }
+
+    let expected = Texture::load(&golden).unwrap_or_else(|e| {
+        panic!(
+            "can't load {}: {e}; regenerate with UPDATE_GOLDENS=1",
+            golden.display()
+        )
+    });
+    assert_eq!([expected.width(), expected.height()], SIZE);
+
+    let differing = expected
+        .pixels()
+        .iter()
+        .zip(&pixels)
+        .filter(|&(&a, &b)| difference(a, b) > THRESHOLD)
+        .count();
+    if differing as f32 > MAX_DIFFERING * pixels.len() as f32 {
+        let actual = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+            .join(name)
+            .with_extension("png");
+        export::save(&actual, width, height, &pixels).unwrap();
+
+        panic!(
+            "{differing} pixels differ from {}; the render is in {}",
+            golden.display(),
+            actual.display()
+        );
+    }
+}
+
+fn view(lat: f32, lon: f32, roll: f32, scale: f32) -> View {
+    View {
+        rot: Rotation::from_center(
+            lat.to_radians(),
+            lon.to_radians(),
+            roll.to_radians(),
+        ),
+        scale,
+        ..View::default()
+    }
+}
+
+#[test]
+fn tangent_at_the_origin() {
+    check(
+        "tangent_origin",
+        ProjectionKind::Tangent,
+        view(0.0, 0.0, 0.0, 24.0),
+    );
+}
+
+#[test]
+fn stereographic_turned() {
+    check(
+        "stereographic_turned",
+        ProjectionKind::Stereographic,
+        view(40.0, -70.0, 15.0, 30.0),
+    );
+}
+
+#[test]
+fn gnomonic_over_the_pole() {
+    check(
+        "gnomonic_pole",
+        ProjectionKind::Gnomonic,
+        view(90.0, 0.0, 0.0, 40.0),
+    );
+}
+
+#[test]
+fn orthographic_zoomed_and_offset() {
+    check(
+        "orthographic_offset",
+        ProjectionKind::Orthographic,
+        View {
+            cam_offset: [20.0, -10.0],
+            ..view(-20.0, 120.0, 0.0, 80.0)
+        },
+    );
+}
+
+#[test]
+fn azimuthal_equidistant_whole_globe() {
+    check(
+        "azimuthal_equidistant",
+        ProjectionKind::AzimuthalEquidistant,
+        view(10.0, 30.0, -30.0, 14.0),
+    );
+}
+
+#[test]
+fn lambert_azimuthal_offset_to_the_edge() {
+    check(
+        "lambert_azimuthal_offset",
+        ProjectionKind::LambertAzimuthal,
+        View {
+            cam_offset: [-48.0, 30.0],
+            ..view(-60.0, 180.0, 90.0, 20.0)
+        },
+    );
+}
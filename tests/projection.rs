@@ -0,0 +1,102 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use tangent_proj::{
+    projection::ProjectionKind,
+    sphere::{self, Rotation},
+};
+
+#[test]
+fn antipode_has_no_image() {
+    for kind in [ProjectionKind::Tangent, ProjectionKind::Stereographic] {
+        let projection = kind.with_scale(100.0);
+
+        assert_eq!(projection.forward(-FRAC_PI_2, 0.3), None, "{kind}");
+        let (x, y) = projection.forward(-FRAC_PI_2 + 1e-3, 0.3).unwrap();
+        // Far out along the meridian, which points right and up.
+        assert!(x > 1e4 && y > 1e4, "{kind}");
+    }
+}
+
+#[test]
+fn centre_maps_to_the_origin() {
+    for kind in ProjectionKind::ALL {
+        let projection = kind.with_scale(100.0);
+
+        // `FRAC_PI_2` rounds up, a hair past the centre.
+        assert_eq!(
+            projection.forward(FRAC_PI_2, 0.3),
+            Some((0.0, 0.0)),
+            "{kind}"
+        );
+    }
+}
+
+#[test]
+fn north_is_up_without_roll() {
+    const STEP: f32 = 1e-2;
+
+    for (lat, lon) in [(0.0, 0.0), (45.0, 10.0), (-60.0, 170.0), (75.0, -90.0)]
+    {
+        let (lat, lon) = (f32::to_radians(lat), f32::to_radians(lon));
+        let to_local = Rotation::from_center(lat, lon, 0.0).inverse().matrix();
+        let projection = ProjectionKind::Orthographic.with_scale(100.0);
+        let image = |lat, lon| {
+            let (lat, lon) =
+                sphere::from_unit(to_local.apply(sphere::to_unit(lat, lon)));
+            projection.forward(lat, lon).unwrap()
+        };
+
+        let (x, y) = image(lat + STEP, lon);
+        assert!(x.abs() < 1e-3 && y > 0.5, "north of {lat}, {lon}: {x}, {y}");
+        let (x, y) = image(lat, lon + STEP);
+        assert!(x > 0.0 && y.abs() < 0.1, "east of {lat}, {lon}: {x}, {y}");
+    }
+}
+
+/// Latitudes and longitudes, in degrees, over the whole sphere but off the
+/// centre, the antipode and the edge of the hemisphere.
+fn points() -> impl Iterator<Item = (f32, f32)> {
+    (-85..=85)
+        .step_by(10)
+        .flat_map(|lat| (-175..=175).step_by(25).map(move |lon| (lat, lon)))
+        .map(|(lat, lon)| {
+            ((lat as f32).to_radians(), (lon as f32).to_radians())
+        })
+}
+
+#[test]
+fn inverse_undoes_forward() {
+    for kind in ProjectionKind::ALL {
+        let projection = kind.with_scale(100.0);
+
+        for (lat, lon) in points() {
+            let Some((x, y)) = projection.forward(lat, lon) else {
+                continue;
+            };
+            let (lat2, lon2) = projection.inverse(x, y).unwrap();
+            let dlon = (lon2 - lon + PI).rem_euclid(2.0 * PI) - PI;
+
+            assert!(
+                (lat2 - lat).abs() < 1e-5 && dlon.abs() < 1e-5,
+                "{kind} at {lat}, {lon}: {lat2}, {lon2}"
+            );
+        }
+    }
+}
+
+#[test]
+fn forward_covers_the_domain() {
+    for kind in ProjectionKind::ALL {
+        let projection = kind.with_scale(100.0);
+
+        for (lat, lon) in points() {
+            let inside = FRAC_PI_2 - lat < projection.domain();
+
+            assert_eq!(
+                projection.forward(lat, lon).is_some(),
+                inside,
+                "{kind} at {lat}, {lon}"
+            );
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use tangent_proj::{
+    mipmap::Mipmaps,
+    parallel::{self, Pool},
+    projection::ProjectionKind,
+    raster::{Renderer, View},
+    reproject::{self, Azimuthal, Georef},
+    sampler::Filter,
+    sphere::Rotation,
+    texture::Texture,
+};
+
+#[test]
+fn equirectangular_to_itself_is_the_identity() {
+    let (width, height) = (64, 32);
+    // Distinct texels, so that any sample from the wrong one shows.
+    let pixels: Vec<u32> = (0..width * height).map(|k| k * 0x01_0305).collect();
+    let image = Mipmaps::new(Texture::new(width, height, pixels.clone()));
+
+    let out = reproject::reproject(
+        &image,
+        &Georef::Equirectangular,
+        &Georef::Equirectangular,
+        [width, height],
+        Filter::Nearest,
+        &Pool::new(parallel::default_threads()),
+    );
+
+    let opaque: Vec<u32> = pixels.iter().map(|px| 0xff00_0000 | px).collect();
+    assert_eq!(out, opaque);
+}
+
+#[test]
+fn unprojected_view_shows_only_its_hemisphere() {
+    // Cells of 45° square, so that sampling twice stays in one of them.
+    let (width, height) = (8, 4);
+    let pixels: Vec<u32> = (0..width * height).map(|k| k * 0x01_0305).collect();
+    let globe = Mipmaps::new(Texture::new(width, height, pixels.clone()));
+
+    // Centred in the cell of column 4 and row 1.
+    let view = View {
+        rot: Rotation::from_center(
+            22.5f32.to_radians(),
+            22.5f32.to_radians(),
+            0.0,
+        ),
+        scale: 24.0,
+        filter: Filter::Nearest,
+        ..View::default()
+    };
+    let rendered = Renderer::new(
+        &globe,
+        &Pool::new(parallel::default_threads()),
+    )
+    .render([64, 64], ProjectionKind::Orthographic, &view);
+
+    let source = Azimuthal {
+        projection: ProjectionKind::Orthographic,
+        rot: view.rot,
+        scale: view.scale,
+        cam_offset: view.cam_offset,
+    };
+    let out = reproject::reproject(
+        &Mipmaps::new(Texture::new(64, 64, rendered)),
+        &Georef::Azimuthal(source),
+        &Georef::Equirectangular,
+        [64, 32],
+        Filter::Nearest,
+        &Pool::new(parallel::default_threads()),
+    );
+    let at = |i: usize, j: usize| out[j * 64 + i];
+
+    // About 22.5° north and east, at the centre.
+    assert_eq!(at(35, 11), 0xff00_0000 | pixels[width as usize + 4]);
+    // Around the antipode, 22.5° south and 157.5° west.
+    for j in 18..23 {
+        for i in 0..8 {
+            assert_eq!(at(i, j), 0, "pixel {i}, {j}");
+        }
+    }
+}
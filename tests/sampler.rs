@@ -0,0 +1,114 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use tangent_proj::{
+    mipmap::Mipmaps,
+    sampler::{AddressMode, Filter, Sampler},
+    texture::Texture,
+};
+
+/// A 4×2 texture whose texels are numbered by position, so a sample tells
+/// which texel it came from.
+fn numbered() -> Mipmaps {
+    Mipmaps::new(Texture::new(4, 2, (0..8).collect()))
+}
+
+fn nearest(mipmaps: &Mipmaps) -> Sampler<'_> {
+    Sampler::new(mipmaps, Filter::Nearest)
+}
+
+#[test]
+fn poles_sample_the_edge_rows() {
+    let mipmaps = numbered();
+    let sampler = nearest(&mipmaps);
+
+    assert_eq!(sampler.sample(FRAC_PI_2, -PI + 0.1, 0.0), 0);
+    // The south pole lies on the edge of the last row, where the texels of
+    // either meridian through it are as near.
+    assert!((4..8).contains(&sampler.sample(-FRAC_PI_2, -PI + 0.1, 0.0)));
+    assert_eq!(sampler.sample(-FRAC_PI_2 + 1e-7, PI - 0.1, 0.0), 7);
+}
+
+#[test]
+fn antimeridian_wraps_around() {
+    let mipmaps = numbered();
+    let sampler = nearest(&mipmaps);
+
+    assert_eq!(sampler.sample(0.5, -PI, 0.0), 0);
+    assert_eq!(sampler.sample(0.5, PI, 0.0), 0);
+    assert_eq!(sampler.sample(0.5, PI - 1e-6, 0.0), 3);
+    assert_eq!(sampler.sample(0.5, 3.0 * PI - 0.1, 0.0), 3);
+}
+
+#[test]
+fn bilinear_blends_across_the_antimeridian() {
+    let texture = Texture::new(4, 1, vec![0x000000, 0, 0, 0xfefefe]);
+    let mipmaps = Mipmaps::new(texture);
+    let sampler = Sampler::new(&mipmaps, Filter::Bilinear);
+
+    assert_eq!(sampler.sample(0.0, PI, 0.0), 0x7f7f7f);
+    assert_eq!(sampler.sample(0.0, -PI, 0.0), 0x7f7f7f);
+}
+
+#[test]
+fn mirror_continues_past_the_pole_on_the_opposite_meridian() {
+    let mipmaps = numbered();
+    let sampler = nearest(&mipmaps);
+
+    // Just past the north pole above the first column is the top of the
+    // third one.
+    assert_eq!(sampler.sample(FRAC_PI_2 + 0.1, -PI + 0.1, 0.0), 2);
+    assert_eq!(sampler.sample(-FRAC_PI_2 - 0.1, -PI + 0.1, 0.0), 6);
+}
+
+#[test]
+fn clamp_and_mirror_without_wrapping_stay_in_the_column() {
+    let mipmaps = numbered();
+    let clamp = nearest(&mipmaps)
+        .with_address_modes(AddressMode::Clamp, AddressMode::Clamp);
+    let mirror = nearest(&mipmaps)
+        .with_address_modes(AddressMode::Clamp, AddressMode::Mirror);
+
+    assert_eq!(clamp.sample(FRAC_PI_2 + 1.0, -PI + 0.1, 0.0), 0);
+    assert_eq!(clamp.sample(0.5, PI + 1.0, 0.0), 3);
+    assert_eq!(clamp.sample(0.5, -PI - 1.0, 0.0), 0);
+    assert_eq!(mirror.sample(FRAC_PI_2 + 0.1, -PI + 0.1, 0.0), 0);
+    assert_eq!(mirror.sample(FRAC_PI_2 + 2.0, -PI + 0.1, 0.0), 4);
+}
+
+#[test]
+fn never_panics() {
+    let mipmaps = numbered();
+    let coords = [
+        0.0,
+        FRAC_PI_2,
+        -FRAC_PI_2,
+        PI,
+        -PI,
+        f32::EPSILON,
+        1e30,
+        -1e30,
+        f32::MAX,
+        f32::MIN,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+    ];
+    let modes = [AddressMode::Wrap, AddressMode::Clamp, AddressMode::Mirror];
+
+    for filter in Filter::ALL {
+        for u in modes {
+            for v in modes {
+                let sampler =
+                    Sampler::new(&mipmaps, filter).with_address_modes(u, v);
+
+                for lat in coords {
+                    for lon in coords {
+                        for footprint in [0.0, 0.1, f32::INFINITY, f32::NAN] {
+                            sampler.sample(lat, lon, footprint);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}